@@ -0,0 +1,229 @@
+//! Point-in-time structural snapshots used by the traversal algorithms.
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap,HashMap};
+use std::sync::Arc;
+
+use super::{Vertex,VertexState};
+
+/// The edges of the graph as slot indices into `vertex_vec`, read once under the graph lock.
+/// Algorithms can then walk the structure without re-locking each vertex.
+pub(crate) struct Adjacency {
+    /// Whether each slot holds a resident vertex
+    pub resident: Vec<bool>,
+    /// Whether each slot has been masked, to be passed over by traversals
+    pub masked: Vec<bool>,
+    /// Destination slots of each slot's outgoing edges, in edge order (ie: its dependencies)
+    pub out: Vec<Vec<usize>>,
+    /// Source slots of each slot's incoming edges (ie: its dependents)
+    pub inc: Vec<Vec<usize>>,
+}
+
+impl Adjacency {
+    pub fn new<K,V,E>(vertex_vec: &[Arc<Vertex<K,V,E>>]) -> Self {
+        let slots: HashMap<*const Vertex<K,V,E>,usize> = vertex_vec.iter().enumerate().map(|(i,v)| (Arc::as_ptr(v), i)).collect();
+
+        let mut adjacency = Adjacency {
+            resident: vec![false; vertex_vec.len()],
+            masked: vertex_vec.iter().map(|v| *v.masked.lock().unwrap()).collect(),
+            out: vec![Vec::new(); vertex_vec.len()],
+            inc: vec![Vec::new(); vertex_vec.len()],
+        };
+
+        for (i, vertex) in vertex_vec.iter().enumerate() {
+            if let VertexState::Resident{ ref edges, .. } = *vertex.state.lock().unwrap() {
+                adjacency.resident[i] = true;
+                for edge in edges.iter() {
+                    let j = slots[&Arc::as_ptr(&edge.dest_vertex)];
+                    adjacency.out[i].push(j);
+                    adjacency.inc[j].push(i);
+                }
+            }
+        }
+        adjacency
+    }
+
+    /// The same slots with every edge reversed, such that dependents take the place of dependencies
+    pub fn transposed(&self) -> Self {
+        Adjacency {
+            resident: self.resident.clone(),
+            masked: self.masked.clone(),
+            out: self.inc.clone(),
+            inc: self.out.clone(),
+        }
+    }
+
+    /// Whether a slot is resident and not masked
+    pub fn active(&self, i: usize) -> bool {
+        self.resident[i] && !self.masked[i]
+    }
+
+    /// Active slots reachable from the active among `roots`, including those roots, by following `edges` (either `out`
+    /// or `inc`) through active slots only
+    pub fn reachable(&self, roots: &[usize], edges: &[Vec<usize>]) -> Vec<bool> {
+        let mut seen = vec![false; edges.len()];
+        self.reach(&mut seen, roots, edges, |_| {});
+        seen
+    }
+
+    /// As `reachable`, marking slots in `seen` and passing over those already marked there. `reached` is called with
+    /// each slot newly marked.
+    pub fn reach<F>(&self, seen: &mut [bool], roots: &[usize], edges: &[Vec<usize>], mut reached: F) where F: FnMut(usize) {
+        let mut stack: Vec<usize> = roots.iter().cloned().filter(|&i| self.active(i)).collect();
+        while let Some(i) = stack.pop() {
+            if seen[i] {
+                continue;
+            }
+            seen[i] = true;
+            reached(i);
+            stack.extend(edges[i].iter().cloned().filter(|&j| !seen[j] && self.active(j)));
+        }
+    }
+
+    /// Distinct dependency slots of a slot in edge order, excluding any self-edge
+    pub fn dependencies(&self, i: usize) -> Vec<usize> {
+        let mut deps: Vec<usize> = Vec::with_capacity(self.out[i].len());
+        for &j in self.out[i].iter() {
+            if j != i && !deps.contains(&j) {
+                deps.push(j);
+            }
+        }
+        deps
+    }
+
+    /// Distinct dependent slots of a slot, excluding any self-edge
+    fn dependents(&self, i: usize) -> Vec<usize> {
+        let mut dependents: Vec<usize> = self.inc[i].iter().cloned().filter(|&j| j != i).collect();
+        dependents.sort();
+        dependents.dedup();
+        dependents
+    }
+
+    /// Active slots ordered such that dependencies precede their dependents, by Kahn's algorithm.
+    ///
+    /// Of the slots whose dependencies have all been ordered, the one with the most incoming edges goes next, as it
+    /// has the most waiting on it, and then the later slot. Where a cycle leaves none such, it is broken at whichever
+    /// member would go first, and the rest of the graph remains correctly ordered around it.
+    pub fn topo_order(&self) -> Vec<usize> {
+        self.ordered(|i| self.active(i), |i| (self.inc[i].len(), i))
+    }
+
+    /// As `topo_order`, of the resident slots whether masked or not
+    pub fn topo_order_resident(&self) -> Vec<usize> {
+        self.ordered(|i| self.resident[i], |i| (self.inc[i].len(), i))
+    }
+
+    /// As `topo_order`, except that of the slots whose dependencies have all been ordered, the lowest `rank` goes next,
+    /// and then the earlier slot
+    pub fn topo_order_ranked(&self, rank: &[usize]) -> Vec<usize> {
+        self.ordered(|i| self.active(i), |i| Reverse((rank[i], i)))
+    }
+
+    /// Kahn's algorithm over the included slots, taking the greatest `priority` of those ready. Should a cycle leave
+    /// none ready, the members of the first strongly connected component still pending are the only candidates, as
+    /// components come dependencies first, and so the greatest of those is taken regardless.
+    fn ordered<F,P,R>(&self, include: F, priority: P) -> Vec<usize> where F: Fn(usize) -> bool, P: Fn(usize) -> R, R: Ord {
+        let included: Vec<usize> = (0..self.out.len()).filter(|&i| include(i)).collect();
+        let mut waiting = vec![0; self.out.len()];
+        for &i in included.iter() {
+            waiting[i] = self.dependencies(i).into_iter().filter(|&j| include(j)).count();
+        }
+        let mut ready: BinaryHeap<(R,usize)> = included.iter().filter(|&&i| waiting[i] == 0).map(|&i| (priority(i), i)).collect();
+        let mut ordered = vec![false; self.out.len()];
+        let mut order = Vec::with_capacity(included.len());
+
+        // Only computed should a cycle stall the ordering
+        let mut components: Option<Vec<Vec<usize>>> = None;
+        let mut stalled = 0;
+
+        while order.len() < included.len() {
+            let i = match ready.pop() {
+                Some((_, i)) => i,
+                None => {
+                    let components = components.get_or_insert_with(|| strongly_connected(&self.out));
+                    let pending = |c: &Vec<usize>| c.iter().any(|&i| include(i) && !ordered[i]);
+                    while !pending(&components[stalled]) {
+                        stalled += 1;
+                    }
+                    components[stalled].iter().cloned().filter(|&i| include(i) && !ordered[i]).max_by_key(|&i| priority(i)).unwrap()
+                },
+            };
+            ordered[i] = true;
+            order.push(i);
+            for j in self.dependents(i).into_iter().filter(|&j| include(j) && !ordered[j]) {
+                waiting[j] -= 1;
+                if waiting[j] == 0 {
+                    ready.push((priority(j), j));
+                }
+            }
+        }
+        order
+    }
+}
+
+/// Strongly connected components of a graph given as adjacency lists, via Tarjan's algorithm.
+///
+/// Components are returned such that every component precedes those with edges into it, which for dependency edges
+/// means dependencies first. Members of each component are in ascending order.
+pub(crate) fn strongly_connected(out: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    let n = out.len();
+    let mut next_index = 0;
+    let mut index: Vec<Option<usize>> = vec![None; n];
+    let mut low = vec![0; n];
+    let mut on_stack = vec![false; n];
+    let mut stack = Vec::new();
+    let mut components = Vec::new();
+
+    for root in 0..n {
+        if index[root].is_some() {
+            continue;
+        }
+        // Explicit call stack of (vertex, position in its edge list)
+        let mut calls = vec![(root, 0)];
+        index[root] = Some(next_index);
+        low[root] = next_index;
+        next_index += 1;
+        stack.push(root);
+        on_stack[root] = true;
+
+        while let Some(&(v, pos)) = calls.last() {
+            if pos < out[v].len() {
+                calls.last_mut().unwrap().1 += 1;
+                let w = out[v][pos];
+                match index[w] {
+                    None => {
+                        index[w] = Some(next_index);
+                        low[w] = next_index;
+                        next_index += 1;
+                        stack.push(w);
+                        on_stack[w] = true;
+                        calls.push((w, 0));
+                    },
+                    Some(w_index) if on_stack[w] => {
+                        low[v] = low[v].min(w_index);
+                    },
+                    Some(_) => {},
+                }
+            }else{
+                calls.pop();
+                if let Some(&(u, _)) = calls.last() {
+                    low[u] = low[u].min(low[v]);
+                }
+                if Some(low[v]) == index[v] {
+                    let mut component = Vec::new();
+                    loop {
+                        let w = stack.pop().unwrap();
+                        on_stack[w] = false;
+                        component.push(w);
+                        if w == v {
+                            break;
+                        }
+                    }
+                    component.sort();
+                    components.push(component);
+                }
+            }
+        }
+    }
+    components
+}
@@ -0,0 +1,94 @@
+//! Structural equality between graphs, regardless of the order in which vertices were inserted or slots reused.
+
+use std::sync::Arc;
+
+use super::{Counts,DependencyGraph,Edge,Vertex,VertexState};
+
+/// Graphs are equal if they have the same resident keys, with equal values and the same edges, in any order.
+/// Phantoms are implied by the edges, and a graph's limits are not compared.
+impl<K,V,E> PartialEq for DependencyGraph<K,V,E> where K: PartialEq, V: PartialEq, E: PartialEq {
+    fn eq(&self, other: &Self) -> bool {
+        if Arc::ptr_eq(&self.vertex_vec, &other.vertex_vec) {
+            return true;
+        }
+        // Lock in a consistent order, in case another thread is comparing the other way round
+        let (first, second) = if (&*self.vertex_vec as *const _) < (&*other.vertex_vec as *const _) {
+            (self, other)
+        } else {
+            (other, self)
+        };
+        let a_vec = first.vertex_vec.lock().unwrap();
+        let b_vec = second.vertex_vec.lock().unwrap();
+        if Counts::get(&a_vec.counts.resident) != Counts::get(&b_vec.counts.resident) {
+            return false;
+        }
+
+        a_vec.iter().all(|a| {
+            // The key is released before comparing edges, as a self-edge would lock it again
+            let b = match *a.key.lock().unwrap() {
+                Some(ref key) => Vertex::find(key, &b_vec),
+                None => return true,
+            };
+            let a_state = a.state.lock().unwrap();
+            let (a_value, a_edges) = match *a_state {
+                VertexState::Resident{ ref value, ref edges } => (value, edges),
+                VertexState::Phantom => return true,
+            };
+            let b_state = match b {
+                Some(b) => b.state.lock().unwrap(),
+                None => return false,
+            };
+            match *b_state {
+                VertexState::Resident{ ref value, ref edges } => value == a_value && same_edges(a_edges, edges),
+                VertexState::Phantom => false,
+            }
+        })
+    }
+}
+
+/// Whether `a` and `b` hold the same (key, measure) pairs as each other, as many times each, in any order
+fn same_edges<K,V,E>(a: &[Edge<K,V,E>], b: &[Edge<K,V,E>]) -> bool where K: PartialEq, E: PartialEq {
+    if a.len() != b.len() {
+        return false;
+    }
+    let mut matched = vec![false; b.len()];
+    a.iter().all(|a_edge| {
+        let a_key = a_edge.dest_vertex.key.lock().unwrap();
+        let found = b.iter().enumerate().position(|(j, b_edge)| {
+            !matched[j] && b_edge.measure == a_edge.measure && *b_edge.dest_vertex.key.lock().unwrap() == *a_key
+        });
+        match found {
+            Some(j) => {
+                matched[j] = true;
+                true
+            },
+            None => false,
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::DependencyGraph;
+
+    #[test]
+    fn eq() {
+        let mut a = DependencyGraph::new();
+        a.insert("A", 1, vec![("Z",None)]);
+        a.insert("B", 2, vec![("A",Some(5)),("B",None),("A",None)]);
+        a.insert("C", 3, vec![]);
+        a.remove("C");
+
+        let mut b = DependencyGraph::new();
+        b.insert("B", 2, vec![("B",None),("A",None),("A",Some(5))]);
+        b.insert("A", 1, vec![("Z",None)]);
+        assert!(a == b);
+        assert!(a == a.clone());
+
+        b.insert("B", 2, vec![("B",None),("A",Some(5)),("A",Some(5))]);
+        assert!(a != b, "edges are compared as a multiset");
+        b.insert("B", 2, vec![("B",None),("A",None),("A",Some(5))]);
+        b.insert("Z", 0, vec![]);
+        assert!(a != b);
+    }
+}
@@ -0,0 +1,104 @@
+//! Weak connectivity, for cheaply ruling out a path before searching for one.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use super::DependencyGraph;
+use adjacency::Adjacency;
+
+/// A point-in-time disjoint-set partition of the graph's vertices, phantoms included, by weak connectivity.
+///
+/// Built by `DependencyGraph::connectivity`, and not updated by later changes to the graph.
+pub struct Connectivity<K> {
+    /// Component of each key, as the root of its set
+    components: HashMap<K,usize>,
+    count: usize,
+}
+
+impl<K> Connectivity<K> where K: Eq+Hash {
+    /// Whether `a` and `b` are connected by edges in either direction. False if either is absent.
+    /// No path can exist between two vertices in different components.
+    pub fn same_component(&self, a: &K, b: &K) -> bool {
+        match (self.components.get(a), self.components.get(b)) {
+            (Some(a), Some(b)) => a == b,
+            _ => false,
+        }
+    }
+    /// Number of weakly connected components
+    pub fn component_count(&self) -> usize {
+        self.count
+    }
+}
+
+impl<K,V,E> DependencyGraph<K,V,E> {
+    /// Partition the graph by weak connectivity, as of now
+    pub fn connectivity(&self) -> Connectivity<K> where K: Eq+Hash+Clone {
+        let vertex_vec = self.vertex_vec.lock().unwrap();
+        let adjacency = Adjacency::new(&vertex_vec);
+
+        let mut parent: Vec<usize> = (0..vertex_vec.len()).collect();
+        let mut size = vec![1; vertex_vec.len()];
+        for (i, out) in adjacency.out.iter().enumerate() {
+            for &j in out.iter() {
+                let (a, b) = (find(&mut parent, i), find(&mut parent, j));
+                if a != b {
+                    // Union by size
+                    let (big, small) = if size[a] < size[b] { (b, a) } else { (a, b) };
+                    parent[small] = big;
+                    size[big] += size[small];
+                }
+            }
+        }
+
+        let mut components = HashMap::with_capacity(vertex_vec.len());
+        let mut counted = vec![false; vertex_vec.len()];
+        let mut count = 0;
+        for (i, vertex) in vertex_vec.iter().enumerate() {
+            if let Some(ref key) = *vertex.key.lock().unwrap() {
+                let root = find(&mut parent, i);
+                if !counted[root] {
+                    counted[root] = true;
+                    count += 1;
+                }
+                components.insert(key.clone(), root);
+            }
+        }
+
+        Connectivity{
+            components,
+            count,
+        }
+    }
+}
+
+/// Root of `i`'s set, halving the path along the way
+fn find(parent: &mut [usize], mut i: usize) -> usize {
+    while parent[i] != i {
+        parent[i] = parent[parent[i]];
+        i = parent[i];
+    }
+    i
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::DependencyGraph;
+
+    #[test]
+    fn same_component() {
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+        graph.insert("A", "Alpha",   vec![("Z",None)]);
+        graph.insert("B", "Bravo",   vec![("Z",None)]);
+        graph.insert("C", "Charlie", vec![("D",None)]);
+        graph.insert("D", "Delta",   vec![]);
+        graph.insert("E", "Echo",    vec![]);
+
+        let connectivity = graph.connectivity();
+        assert!(connectivity.same_component(&"A", &"B"), "connected through the phantom");
+        assert!(connectivity.same_component(&"D", &"C"));
+        assert!(!connectivity.same_component(&"A", &"C"));
+        assert!(connectivity.same_component(&"E", &"E"));
+        assert!(!connectivity.same_component(&"E", &"F"));
+        assert_eq!(connectivity.component_count(), 3);
+    }
+}
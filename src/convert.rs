@@ -0,0 +1,202 @@
+//! Conversions from ad-hoc dependency representations.
+
+use std::collections::{BTreeMap,HashMap};
+use std::convert::TryFrom;
+use std::hash::BuildHasher;
+use std::iter::FromIterator;
+
+use super::{DependencyGraph,Edges};
+use adjacency::strongly_connected;
+use limits;
+
+/// A `(key, value, edges)` record for bulk construction, with edges as given to `insert`
+type Record<K,V,E> = (K,V,Edges<K,E>);
+
+/// A problem found while validating bulk input. Indices refer to positions in the input `Vec`.
+#[derive(Debug,PartialEq,Eq)]
+pub enum BuildError {
+    /// The record at `duplicate` repeats the key of the record at `first`
+    DuplicateKey { first: usize, duplicate: usize },
+    /// Edge number `edge` of the record at `index` points back at the record itself
+    SelfLoop { index: usize, edge: usize },
+    /// These records depend upon one another circularly. Only reported by `try_from_strict`
+    Cycle { indices: Vec<usize> },
+}
+
+/// Build a graph from a map of key to value and dependency keys. The resulting edges carry no measure.
+impl<K,V,E,S> From<HashMap<K,(V,Vec<K>),S>> for DependencyGraph<K,V,E> where K: PartialEq, S: BuildHasher {
+    fn from(map: HashMap<K,(V,Vec<K>),S>) -> Self {
+        let mut graph = DependencyGraph::new();
+        for (key, (value, deps)) in map {
+            graph.insert_attributed(key, value, deps.into_iter().map(|k| (k,None)).collect(), None, drop);
+        }
+        graph
+    }
+}
+
+/// Build a graph from a map of key to value and dependency keys. The resulting edges carry no measure.
+impl<K,V,E> From<BTreeMap<K,(V,Vec<K>)>> for DependencyGraph<K,V,E> where K: PartialEq {
+    fn from(map: BTreeMap<K,(V,Vec<K>)>) -> Self {
+        let mut graph = DependencyGraph::new();
+        for (key, (value, deps)) in map {
+            graph.insert_attributed(key, value, deps.into_iter().map(|k| (k,None)).collect(), None, drop);
+        }
+        graph
+    }
+}
+
+/// Insert each `(key, value, edges)` tuple, as `insert`, under a single acquisition of the graph lock. The iterator must
+/// not use the graph meanwhile, through this or any other handle.
+///
+/// Panics if an insert would exceed this handle's `Limits`, leaving those before it inserted.
+impl<K,V,E> Extend<Record<K,V,E>> for DependencyGraph<K,V,E> where K: PartialEq {
+    fn extend<I>(&mut self, iter: I) where I: IntoIterator<Item=Record<K,V,E>> {
+        let mut vertex_vec = self.vertex_vec.lock().unwrap();
+        for (key, value, edge_tuples) in iter {
+            if let Err(error) = self.limits.check(&vertex_vec, &key, &edge_tuples) {
+                // Released first, so as not to poison it
+                drop(vertex_vec);
+                limits::exceeded(error);
+            }
+            let (_, released) = vertex_vec.insert(key, value, edge_tuples, None);
+            drop(released);
+        }
+    }
+}
+
+/// Build a graph from `(key, value, edges)` tuples, as by `extend`. Later tuples for a key replace earlier ones.
+impl<K,V,E> FromIterator<Record<K,V,E>> for DependencyGraph<K,V,E> where K: PartialEq {
+    fn from_iter<I>(iter: I) -> Self where I: IntoIterator<Item=Record<K,V,E>> {
+        let mut graph = DependencyGraph::new();
+        graph.extend(iter);
+        graph
+    }
+}
+
+/// Build a graph from `(key, value, edges)` records, failing with every duplicate key and self-loop found.
+/// Cycles spanning several records are permitted; use `DependencyGraph::try_from_strict` to reject those too.
+impl<K,V,E> TryFrom<Vec<Record<K,V,E>>> for DependencyGraph<K,V,E> where K: Ord {
+    type Error = Vec<BuildError>;
+
+    fn try_from(records: Vec<Record<K,V,E>>) -> Result<Self,Self::Error> {
+        DependencyGraph::validated(records, false)
+    }
+}
+
+impl<K,V,E> DependencyGraph<K,V,E> {
+    /// As `try_from`, additionally failing if the records contain any cycle.
+    pub fn try_from_strict(records: Vec<Record<K,V,E>>) -> Result<Self,Vec<BuildError>> where K: Ord {
+        DependencyGraph::validated(records, true)
+    }
+
+    fn validated(records: Vec<Record<K,V,E>>, strict: bool) -> Result<Self,Vec<BuildError>> where K: Ord {
+        let mut errors = Vec::new();
+        {
+            let mut positions: BTreeMap<&K,usize> = BTreeMap::new();
+            for (i, record) in records.iter().enumerate() {
+                match positions.get(&record.0) {
+                    Some(&first) => errors.push(BuildError::DuplicateKey{ first, duplicate: i }),
+                    None => {
+                        positions.insert(&record.0, i);
+                    }
+                }
+                for (e, edge) in record.2.iter().enumerate() {
+                    if edge.0 == record.0 {
+                        errors.push(BuildError::SelfLoop{ index: i, edge: e });
+                    }
+                }
+            }
+
+            if strict && errors.is_empty() {
+                let out: Vec<Vec<usize>> = records.iter()
+                    .map(|r| r.2.iter().filter_map(|e| positions.get(&e.0).cloned()).collect())
+                    .collect();
+                for indices in strongly_connected(&out) {
+                    if indices.len() > 1 {
+                        errors.push(BuildError::Cycle{ indices });
+                    }
+                }
+            }
+        }
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        let mut graph = DependencyGraph::new();
+        for (key, value, edges) in records {
+            graph.insert_attributed(key, value, edges, None, drop);
+        }
+        Ok(graph)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::{BTreeMap,HashMap};
+    use std::convert::TryFrom;
+    use super::BuildError;
+    use super::super::DependencyGraph;
+
+    #[test]
+    fn from_maps() {
+        let mut hash_map = HashMap::new();
+        hash_map.insert("A", ("Alpha", vec![]));
+        hash_map.insert("B", ("Bravo", vec!["A"]));
+        hash_map.insert("C", ("Charlie", vec!["A","B","Z"]));
+        let btree_map: BTreeMap<_,_> = hash_map.clone().into_iter().collect();
+
+        for graph in [DependencyGraph::<_,_,()>::from(hash_map), DependencyGraph::from(btree_map)] {
+            let dep_counts = graph.eval(|_, value, deps| (*value, deps.len()));
+            assert_eq!(dep_counts.len(), 3);
+            assert_eq!(dep_counts["A"], ("Alpha", 0));
+            assert_eq!(dep_counts["B"], ("Bravo", 1));
+            assert_eq!(dep_counts["C"], ("Charlie", 2));
+        }
+    }
+
+    #[test]
+    fn try_from_validation() {
+        let records = vec![
+            ("A", 1, vec![]),
+            ("B", 2, vec![("A",Some(1.0)),("C",None)]),
+            ("C", 3, vec![("B",Some(2.0))]),
+        ];
+        let graph = DependencyGraph::try_from(records.clone()).expect("cycles are permitted");
+        assert_eq!(graph.eval(|_, value, _: Vec<&i32>| *value).len(), 3);
+
+        assert_eq!(DependencyGraph::try_from_strict(records).err(), Some(vec![BuildError::Cycle{ indices: vec![1,2] }]));
+
+        let records = vec![
+            ("A", 1, vec![("A",Some(1.0))]),
+            ("B", 2, vec![]),
+            ("A", 3, vec![("B",None)]),
+        ];
+        assert_eq!(DependencyGraph::try_from(records).err(), Some(vec![
+            BuildError::SelfLoop{ index: 0, edge: 0 },
+            BuildError::DuplicateKey{ first: 0, duplicate: 2 },
+        ]));
+    }
+
+    #[test]
+    fn extend() {
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+        graph.insert("A", "Alpha", vec![]);
+        graph.extend(vec![
+            ("B", "Bravo", vec![("A",None)]),
+            ("C", "Charlie", vec![("B",None),("Z",None)]),
+        ]);
+
+        let keys: Vec<_> = graph.iter().map(|i| i.key).collect();
+        assert_eq!(keys, vec!["A","B","C"]);
+        assert_eq!(graph.phantom_count(), 1);
+    }
+
+    #[test]
+    fn from_iter() {
+        let graph: DependencyGraph<_,_,()> = vec![("B", "Bravo", vec![("A",None)]), ("A", "Alpha", vec![])]
+            .into_iter()
+            .collect();
+        let keys: Vec<_> = graph.iter().map(|i| i.key).collect();
+        assert_eq!(keys, vec!["A","B"]);
+    }
+}
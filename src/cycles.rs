@@ -0,0 +1,270 @@
+//! Explicit handling of cycles, for callers which can't accept them being broken arbitrarily.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::Arc;
+
+use super::{DependencyGraph,TopoIter,Vertex,VertexState};
+use adjacency::{Adjacency,strongly_connected};
+
+/// What `iter_with` does about cycles among the unmasked resident vertices
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum CyclePolicy {
+    /// Fail with every cycle found, rather than iterating
+    ErrorOut,
+    /// Order the members of each cycle arbitrarily among themselves, as `iter` does
+    BreakArbitraryEdge,
+    /// As `BreakArbitraryEdge`, additionally yielding the members of each cycle one after another, with nothing between,
+    /// and each with the cycle's index as its `group`. Vertices outside any cycle keep the order `iter` gives them
+    EmitSccAsGroup,
+    /// Don't yield the members of any cycle. Their dependents are still yielded, as if those dependencies were phantoms
+    SkipCycleMembers,
+}
+
+/// The cycles which prevented iteration under `CyclePolicy::ErrorOut`
+#[derive(Clone,Debug,PartialEq)]
+pub struct CycleError<K> {
+    /// The keys of each cycle's members, in no particular order. A vertex with an edge to itself forms a cycle alone
+    pub cycles: Vec<Vec<K>>,
+}
+
+impl<K,V,E> DependencyGraph<K,V,E> {
+    /// As `iter`, with cycles handled according to `policy`
+    pub fn iter_with(&self, policy: CyclePolicy) -> Result<TopoIter<K,V,E>,CycleError<K>> where K: Clone, V: Clone {
+        let vertex_vec = self.vertex_vec.lock().unwrap();
+        let adjacency = Adjacency::new(&vertex_vec);
+        let all = vec![true; vertex_vec.len()];
+        let components = active_components(&adjacency);
+        let mut group = vec![None; vertex_vec.len()];
+        let order = match policy {
+            CyclePolicy::ErrorOut => {
+                let cycles: Vec<Vec<K>> = components.iter()
+                    .filter(|c| is_cycle(&adjacency, c))
+                    .map(|c| keys(&vertex_vec, c))
+                    .collect();
+                if !cycles.is_empty() {
+                    return Err(CycleError{ cycles });
+                }
+                adjacency.topo_order()
+            },
+            CyclePolicy::BreakArbitraryEdge => adjacency.topo_order(),
+            CyclePolicy::EmitSccAsGroup => {
+                let mut component_of = vec![None; vertex_vec.len()];
+                for (c, component) in components.iter().enumerate().filter(|(_, c)| is_cycle(&adjacency, c)) {
+                    for &i in component {
+                        component_of[i] = Some(c);
+                    }
+                }
+                // Each cycle goes where `iter` first reaches one of its members. Ordering only stalls on a cycle once
+                // everything outside it that the cycle depends on has been ordered, so its other members are due too
+                let mut groups = 0;
+                let mut order = Vec::new();
+                for i in adjacency.topo_order() {
+                    match component_of[i] {
+                        None => order.push(i),
+                        Some(c) if group[i].is_none() => {
+                            for &j in components[c].iter() {
+                                group[j] = Some(groups);
+                                order.push(j);
+                            }
+                            groups += 1;
+                        },
+                        Some(_) => {},
+                    }
+                }
+                order
+            },
+            CyclePolicy::SkipCycleMembers => {
+                let mut included = all;
+                for component in components.iter().filter(|c| is_cycle(&adjacency, c)) {
+                    for &i in component {
+                        included[i] = false;
+                    }
+                }
+                return Ok(TopoIter::restricted(&vertex_vec, &adjacency, &included, |value, _| value.clone()));
+            },
+        };
+        let mut iter = TopoIter::ordered(&vertex_vec, &adjacency, order, &all, |value, _| value.clone());
+        for ordered in iter.sorted.iter_mut() {
+            ordered.item.group = group[ordered.slot];
+        }
+        Ok(iter)
+    }
+
+    /// The unmasked resident vertices grouped into their strongly connected components, as of now. Each vertex outside
+    /// any cycle forms a group of its own. Groups are yielded dependencies first, and no two of them form a cycle.
+    pub fn iter_scc(&self) -> std::vec::IntoIter<Vec<(K,V)>> where K: Clone, V: Clone {
+        let vertex_vec = self.vertex_vec.lock().unwrap();
+        let adjacency = Adjacency::new(&vertex_vec);
+        let groups: Vec<Vec<(K,V)>> = active_components(&adjacency).into_iter().map(|component| {
+            component.into_iter().filter_map(|i| {
+                let key = vertex_vec[i].key.lock().unwrap().clone()?;
+                match *vertex_vec[i].state.lock().unwrap() {
+                    VertexState::Resident{ ref value, .. } => Some((key, value.clone())),
+                    VertexState::Phantom => None,
+                }
+            }).collect()
+        }).collect();
+        groups.into_iter()
+    }
+
+    /// The strongly connected components of the resident vertices, masked or not, as keys. Components are ordered
+    /// dependencies first, and a vertex in no cycle forms a component of its own, so the cycles are those of more than
+    /// one key, or of a key with an edge to itself.
+    pub fn scc(&self) -> Vec<Vec<K>> where K: Clone {
+        let vertex_vec = self.vertex_vec.lock().unwrap();
+        let adjacency = Adjacency::new(&vertex_vec);
+        // Phantoms have no edges, and so are never part of a larger component
+        strongly_connected(&adjacency.out).into_iter()
+            .filter(|c| adjacency.resident[c[0]])
+            .map(|c| keys(&vertex_vec, &c))
+            .collect()
+    }
+
+    /// Collapse each of the components given by `scc` into a single vertex, keyed by its position among them, and
+    /// valued with its members' values in the same order as their keys. Edges between components are retained with
+    /// their measures, including duplicates, and those within a component or to a phantom are dropped, so the result
+    /// is acyclic. Also returns the component of each resident key.
+    pub fn condense(&self) -> (DependencyGraph<usize,Vec<V>,E>, HashMap<K,usize>) where K: Eq+Hash+Clone, V: Clone, E: Clone {
+        let mut condensed = Vec::new();
+        let mut component_of = HashMap::new();
+        {
+            let vertex_vec = self.vertex_vec.lock().unwrap();
+            let adjacency = Adjacency::new(&vertex_vec);
+            let components: Vec<Vec<usize>> = strongly_connected(&adjacency.out).into_iter()
+                .filter(|c| adjacency.resident[c[0]])
+                .collect();
+            let mut slot_component = vec![None; vertex_vec.len()];
+            for (c, component) in components.iter().enumerate() {
+                for &i in component {
+                    slot_component[i] = Some(c);
+                }
+            }
+
+            for (c, component) in components.iter().enumerate() {
+                let mut values = Vec::with_capacity(component.len());
+                let mut edges = Vec::new();
+                for &i in component {
+                    if let VertexState::Resident{ ref value, edges: ref vertex_edges } = *vertex_vec[i].state.lock().unwrap() {
+                        values.push(value.clone());
+                        for (edge, &j) in vertex_edges.iter().zip(adjacency.out[i].iter()) {
+                            match slot_component[j] {
+                                Some(d) if d != c => edges.push((d, edge.measure.clone())),
+                                _ => {},
+                            }
+                        }
+                    }
+                    if let Some(ref key) = *vertex_vec[i].key.lock().unwrap() {
+                        component_of.insert(key.clone(), c);
+                    }
+                }
+                condensed.push((c, values, edges));
+            }
+        }
+        (condensed.into_iter().collect(), component_of)
+    }
+}
+
+/// Strongly connected components of the unmasked resident slots, dependencies first
+fn active_components(adjacency: &Adjacency) -> Vec<Vec<usize>> {
+    let out: Vec<Vec<usize>> = adjacency.out.iter().enumerate().map(|(i, out)| {
+        if adjacency.active(i) { out.iter().cloned().filter(|&j| adjacency.active(j)).collect() } else { Vec::new() }
+    }).collect();
+    strongly_connected(&out).into_iter().filter(|c| adjacency.active(c[0])).collect()
+}
+
+fn is_cycle(adjacency: &Adjacency, component: &[usize]) -> bool {
+    component.len() > 1 || adjacency.out[component[0]].contains(&component[0])
+}
+
+fn keys<K,V,E>(vertex_vec: &[Arc<Vertex<K,V,E>>], slots: &[usize]) -> Vec<K> where K: Clone {
+    slots.iter().filter_map(|&i| vertex_vec[i].key.lock().unwrap().clone()).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{CyclePolicy,CycleError};
+    use super::super::DependencyGraph;
+
+    fn keys(graph: &DependencyGraph<&'static str,&'static str,()>, policy: CyclePolicy) -> Result<Vec<&'static str>,CycleError<&'static str>> {
+        graph.iter_with(policy).map(|iter| iter.map(|i| i.key).collect())
+    }
+
+    #[test]
+    fn iter_with() {
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+        graph.insert("A", "Alpha",   vec![]);
+        graph.insert("B", "Bravo",   vec![("A",None),("C",None)]);
+        graph.insert("E", "Echo",    vec![("A",None)]);
+        graph.insert("C", "Charlie", vec![("B",None)]);
+        graph.insert("D", "Delta",   vec![("C",None)]);
+
+        let mut cycles = keys(&graph, CyclePolicy::ErrorOut).unwrap_err().cycles;
+        cycles[0].sort();
+        assert_eq!(cycles, vec![vec!["B","C"]]);
+        assert_eq!(keys(&graph, CyclePolicy::BreakArbitraryEdge), Ok(graph.iter().map(|i| i.key).collect()));
+        assert_eq!(keys(&graph, CyclePolicy::SkipCycleMembers), Ok(vec!["A","E","D"]));
+
+        let grouped = keys(&graph, CyclePolicy::EmitSccAsGroup).unwrap();
+        let b = grouped.iter().position(|&k| k == "B").unwrap();
+        let c = grouped.iter().position(|&k| k == "C").unwrap();
+        assert_eq!(b.max(c) - b.min(c), 1);
+        assert!(grouped.iter().position(|&k| k == "D").unwrap() > b.max(c));
+        let singletons = |keys: Vec<&'static str>| keys.into_iter().filter(|&k| k != "B" && k != "C").collect::<Vec<_>>();
+        assert_eq!(singletons(grouped), singletons(graph.iter().map(|i| i.key).collect()));
+        let groups: Vec<_> = graph.iter_with(CyclePolicy::EmitSccAsGroup).unwrap().map(|i| (i.key, i.group)).collect();
+        assert!(groups.iter().all(|&(k, g)| g == if k == "B" || k == "C" { Some(0) } else { None }));
+        assert!(graph.iter().all(|i| i.group.is_none()));
+
+        graph.remove("C");
+        assert!(keys(&graph, CyclePolicy::ErrorOut).is_ok());
+    }
+
+    #[test]
+    fn iter_scc() {
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+        graph.insert("A", 1, vec![("Z",None)]);
+        graph.insert("B", 2, vec![("A",None),("C",None)]);
+        graph.insert("C", 3, vec![("B",None)]);
+        graph.insert("D", 4, vec![("C",None)]);
+
+        let mut groups: Vec<Vec<_>> = graph.iter_scc().collect();
+        groups[1].sort();
+        assert_eq!(groups, vec![vec![("A",1)], vec![("B",2),("C",3)], vec![("D",4)]]);
+    }
+
+    #[test]
+    fn scc() {
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+        graph.insert("A", "Alpha",   vec![("Z",None),("A",None)]);
+        graph.insert("B", "Bravo",   vec![("A",None),("C",None)]);
+        graph.insert("C", "Charlie", vec![("B",None)]);
+        graph.mask(&"C");
+
+        let mut components = graph.scc();
+        components[1].sort();
+        assert_eq!(components, vec![vec!["A"], vec!["B","C"]]);
+    }
+
+    #[test]
+    fn condense() {
+        let mut graph = DependencyGraph::new();
+        graph.insert("A", 1, vec![("Z",None)]);
+        graph.insert("B", 2, vec![("A",Some(5)),("C",None)]);
+        graph.insert("C", 3, vec![("B",None),("A",Some(6))]);
+        graph.insert("D", 4, vec![("C",None)]);
+
+        let (condensed, component_of) = graph.condense();
+        let (a, b, d) = (component_of[&"A"], component_of[&"B"], component_of[&"D"]);
+        assert_eq!(component_of[&"C"], b);
+        assert_eq!(condensed.len(), 3);
+        let mut edges = condensed.edges(&b);
+        edges.sort();
+        assert_eq!(edges, vec![(a,Some(5)),(a,Some(6))]);
+        assert_eq!(condensed.edges(&d), vec![(b,None)]);
+        let mut values = condensed.get(&b).unwrap();
+        values.sort();
+        assert_eq!(values, vec![2,3]);
+        assert!(condensed.iter_with(super::CyclePolicy::ErrorOut).is_ok());
+    }
+}
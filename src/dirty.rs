@@ -0,0 +1,83 @@
+//! Dirty-marking for make-like consumers: mark what changed, then rebuild it and everything downstream.
+
+use std::borrow::Borrow;
+
+use super::{DependencyGraph,Vertex,VertexState};
+use adjacency::Adjacency;
+
+impl<K,V,E> DependencyGraph<K,V,E> {
+    /// Mark a resident vertex as dirty. Returns false if the key is not resident.
+    pub fn mark_dirty<Q>(&self, key: &Q) -> bool where K: Borrow<Q>, Q: ?Sized+PartialEq {
+        self.set_dirty(key, true)
+    }
+
+    /// Clear the dirty mark on a resident vertex, typically once it has been rebuilt. Returns false if the key is not resident.
+    pub fn clear_dirty<Q>(&self, key: &Q) -> bool where K: Borrow<Q>, Q: ?Sized+PartialEq {
+        self.set_dirty(key, false)
+    }
+
+    fn set_dirty<Q>(&self, key: &Q, dirty: bool) -> bool where K: Borrow<Q>, Q: ?Sized+PartialEq {
+        let vertex_vec = self.vertex_vec.lock().unwrap();
+        match Vertex::find(key, &vertex_vec) {
+            Some(vertex) => {
+                if let VertexState::Phantom = *vertex.state.lock().unwrap() {
+                    return false;
+                }
+                *vertex.dirty.lock().unwrap() = dirty;
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Every dirty vertex plus all of their transitive dependents, in topological order.
+    /// Dependents are included whether or not they are themselves marked dirty.
+    pub fn dirty_closure(&self) -> Vec<K> where K: Clone {
+        let vertex_vec = self.vertex_vec.lock().unwrap();
+        let adjacency = Adjacency::new(&vertex_vec);
+
+        let mut included = vec![false; vertex_vec.len()];
+        let mut stack: Vec<usize> = (0..vertex_vec.len())
+            .filter(|&i| adjacency.resident[i] && *vertex_vec[i].dirty.lock().unwrap())
+            .collect();
+        while let Some(i) = stack.pop() {
+            if !included[i] {
+                included[i] = true;
+                stack.extend(adjacency.inc[i].iter().cloned());
+            }
+        }
+
+        adjacency.topo_order().into_iter()
+            .filter(|&i| included[i])
+            .filter_map(|i| vertex_vec[i].key.lock().unwrap().clone())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::DependencyGraph;
+
+    #[test]
+    fn dirty_closure() {
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+        graph.insert("A", "Alpha",   vec![]);
+        graph.insert("B", "Bravo",   vec![("A",None)]);
+        graph.insert("C", "Charlie", vec![("B",None)]);
+        graph.insert("D", "Delta",   vec![]);
+        graph.insert("E", "Echo",    vec![("D",None),("Z",None)]);
+
+        assert!(graph.dirty_closure().is_empty());
+        assert!(!graph.mark_dirty(&"Z"), "phantoms cannot be dirtied");
+
+        assert!(graph.mark_dirty(&"B"));
+        assert!(graph.mark_dirty(&"D"));
+        let closure = graph.dirty_closure();
+        assert_eq!(closure.len(), 4);
+        assert!(closure.iter().position(|k| *k == "B") < closure.iter().position(|k| *k == "C"));
+        assert!(closure.iter().position(|k| *k == "D") < closure.iter().position(|k| *k == "E"));
+
+        assert!(graph.clear_dirty(&"D"));
+        assert_eq!(graph.dirty_closure(), vec!["B","C"]);
+    }
+}
@@ -0,0 +1,196 @@
+//! Inspection and editing of individual edges, without re-inserting the vertex they belong to.
+
+use std::borrow::Borrow;
+
+use super::{DependencyGraph,Edge,Edges,LimitError,Vertex,VertexState};
+
+impl<K,V,E> DependencyGraph<K,V,E> {
+    /// The edges of a resident vertex in order, as destination keys and measures. Empty if the key is not resident.
+    /// See `edges_with_provenance` for who created each edge.
+    pub fn edges<Q>(&self, key: &Q) -> Edges<K,E> where K: Borrow<Q>+Clone, Q: ?Sized+PartialEq, E: Clone {
+        let vertex_vec = self.vertex_vec.lock().unwrap();
+        let vertex = match Vertex::find(key, &vertex_vec) {
+            Some(vertex) => vertex,
+            None => return Vec::new(),
+        };
+        let state = vertex.state.lock().unwrap();
+        match *state {
+            VertexState::Resident{ ref edges, .. } => {
+                edges.iter().filter_map(|e| Some((e.dest_vertex.key.lock().unwrap().clone()?, e.measure.clone()))).collect()
+            },
+            VertexState::Phantom => Vec::new(),
+        }
+    }
+
+    /// Add an edge from the resident vertex `from` to `to`, which is created as a phantom if not already present.
+    /// Returns false, adding nothing, if `from` is not resident.
+    ///
+    /// Panics if the edge would exceed this handle's `Limits`. Use `try_add_edge` where those are configured.
+    pub fn add_edge(&mut self, from: &K, to: K, measure: Option<E>) -> bool where K: PartialEq {
+        match self.try_add_edge(from, to, measure) {
+            Ok(added) => added,
+            Err(error) => panic!("insert exceeded the graph's limits: {:?}", error),
+        }
+    }
+    /// As `add_edge`, but failing, with the graph unchanged, if the edge would exceed this handle's `Limits`. The edges
+    /// `from` already has count towards `max_edges_per_vertex`.
+    pub fn try_add_edge(&mut self, from: &K, to: K, measure: Option<E>) -> Result<bool,LimitError> where K: PartialEq {
+        let mut vertex_vec = self.vertex_vec.lock().unwrap();
+        let vertex = match Vertex::find(from, &vertex_vec) {
+            Some(vertex) if vertex.is_resident() => vertex.clone(),
+            _ => return Ok(false),
+        };
+        let existing = match *vertex.state.lock().unwrap() {
+            VertexState::Resident{ ref edges, .. } => edges.len(),
+            VertexState::Phantom => unreachable!(),
+        };
+        let edge_tuples = [(to, measure)];
+        self.limits.check_appended(&vertex_vec, from, existing, &edge_tuples)?;
+        let [(to, measure)] = edge_tuples;
+        let edge = Edge::new(to, measure, None, &mut vertex_vec);
+        if let VertexState::Resident{ ref mut edges, .. } = *vertex.state.lock().unwrap() {
+            edges.push(edge);
+        }
+        Ok(true)
+    }
+
+    /// Remove the first edge from `from` to `to`, returning false if there was none.
+    /// `to` is reclaimed if it's a phantom which nothing else references.
+    pub fn remove_edge<Q>(&mut self, from: &Q, to: &Q) -> bool where K: Borrow<Q>, Q: ?Sized+PartialEq {
+        self.remove_edges_limited(from, to, 1) > 0
+    }
+
+    /// As `remove_edge`, removing every edge from `from` to `to`, and returning the number removed
+    pub fn remove_edges<Q>(&mut self, from: &Q, to: &Q) -> usize where K: Borrow<Q>, Q: ?Sized+PartialEq {
+        self.remove_edges_limited(from, to, usize::MAX)
+    }
+
+    /// Set the measure of every edge from `from` to `to`, returning the number of edges updated
+    pub fn set_edge_measure<Q>(&self, from: &Q, to: &Q, measure: Option<E>) -> usize where K: Borrow<Q>, Q: ?Sized+PartialEq, E: Clone {
+        self.update_edge_measure(from, to, |m| *m = measure.clone())
+    }
+
+    /// Update the measure of every edge from `from` to `to` in place, returning the number of edges updated.
+    /// The vertex is locked meanwhile, so `update` must not call back into the graph.
+    pub fn update_edge_measure<F,Q>(&self, from: &Q, to: &Q, mut update: F) -> usize where K: Borrow<Q>, Q: ?Sized+PartialEq, F: FnMut(&mut Option<E>) {
+        let vertex_vec = self.vertex_vec.lock().unwrap();
+        let vertex = match Vertex::find(from, &vertex_vec) {
+            Some(vertex) => vertex,
+            None => return 0,
+        };
+        let mut updated = 0;
+        if let VertexState::Resident{ ref mut edges, .. } = *vertex.state.lock().unwrap() {
+            for edge in edges.iter_mut() {
+                if edge.dest_vertex.key.lock().unwrap().as_ref().map(Borrow::borrow) == Some(to) {
+                    update(&mut edge.measure);
+                    updated += 1;
+                }
+            }
+        }
+        updated
+    }
+
+    fn remove_edges_limited<Q>(&mut self, from: &Q, to: &Q, limit: usize) -> usize where K: Borrow<Q>, Q: ?Sized+PartialEq {
+        let vertex_vec = self.vertex_vec.lock().unwrap();
+        let vertex = match Vertex::find(from, &vertex_vec) {
+            Some(vertex) => vertex,
+            None => return 0,
+        };
+        let mut removed = Vec::new();
+        if let VertexState::Resident{ ref mut edges, .. } = *vertex.state.lock().unwrap() {
+            let mut i = 0;
+            while i < edges.len() && removed.len() < limit {
+                if edges[i].dest_vertex.key.lock().unwrap().as_ref().map(Borrow::borrow) == Some(to) {
+                    removed.push(edges.remove(i));
+                }else{
+                    i += 1;
+                }
+            }
+        }
+        // Dropped only now that the state lock is released, as the edge may point back at its own vertex
+        removed.len()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::{DependencyGraph,Limits,LimitError,VertexStatus};
+
+    #[test]
+    fn edges() {
+        let mut graph = DependencyGraph::new();
+        graph.insert("B", "Bravo", vec![]);
+        graph.insert("A", "Alpha", vec![("Z",Some(1)),("B",None),("A",Some(2)),("Z",Some(3))]);
+
+        assert_eq!(graph.edges(&"A"), vec![("Z",Some(1)),("B",None),("A",Some(2)),("Z",Some(3))], "in order, repeats and all");
+        assert!(graph.edges(&"B").is_empty());
+        assert!(graph.edges(&"Z").is_empty(), "phantoms have no edges");
+        assert!(graph.edges(&"Y").is_empty());
+
+        // Removing a destination leaves it a phantom, still listed
+        graph.remove("B");
+        assert_eq!(graph.edges(&"A").len(), 4);
+        assert_eq!(graph.status(&"B"), VertexStatus::Removed);
+    }
+
+    #[test]
+    fn add_edge() {
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+        graph.insert("A", "Alpha", vec![]);
+        graph.insert("B", "Bravo", vec![("A",None)]);
+
+        assert!(graph.add_edge(&"A", "Z", None));
+        assert!(!graph.add_edge(&"Z", "A", None), "phantoms have no edges");
+        assert_eq!(graph.status(&"Z"), VertexStatus::Phantom);
+        assert_eq!(graph.edge_count(), 2);
+        assert_eq!(graph.distance_map(&"B").len(), 3);
+
+        // The edge is released like any other when A is re-inserted
+        graph.insert("A", "Alpha", vec![]);
+        assert_eq!(graph.status(&"Z"), VertexStatus::Missing);
+    }
+
+    #[test]
+    fn add_edge_limit() {
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::with_limits(Limits{
+            max_edges_per_vertex: Some(2),
+            ..Limits::default()
+        });
+        graph.insert("A", "Alpha", vec![("X",None)]);
+
+        assert_eq!(graph.try_add_edge(&"A", "Y", None), Ok(true));
+        assert_eq!(graph.try_add_edge(&"A", "Z", None), Err(LimitError::EdgesPerVertex{ limit: 2 }));
+        assert_eq!(graph.try_add_edge(&"Z", "A", None), Ok(false));
+        assert_eq!(graph.edges(&"A"), vec![("X",None),("Y",None)]);
+        assert!(!graph.contains_key(&"Z"), "failed additions leave the graph unchanged");
+    }
+
+    #[test]
+    fn remove_edge() {
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+        graph.insert("A", "Alpha", vec![("Z",None),("Z",None),("Y",None),("A",None)]);
+
+        assert!(graph.remove_edge(&"A", &"Z"));
+        assert_eq!(graph.status(&"Z"), VertexStatus::Phantom, "still referenced by the second edge");
+        assert_eq!(graph.remove_edges(&"A", &"Z"), 1);
+        assert_eq!(graph.status(&"Z"), VertexStatus::Missing);
+        assert!(!graph.remove_edge(&"A", &"Z"));
+
+        assert!(graph.remove_edge(&"A", &"A"));
+        assert_eq!(graph.remove_edges(&"Y", &"A"), 0);
+        assert_eq!((graph.edge_count(), graph.phantom_count()), (1, 1));
+    }
+
+    #[test]
+    fn edge_measure() {
+        let mut graph = DependencyGraph::new();
+        graph.insert("A", "Alpha", vec![("B",Some(1)),("C",Some(2)),("B",None)]);
+
+        assert_eq!(graph.set_edge_measure(&"A", &"C", Some(5)), 1);
+        assert_eq!(graph.update_edge_measure(&"A", &"B", |m| *m = Some(m.unwrap_or(0) + 10)), 2);
+        assert_eq!(graph.set_edge_measure(&"B", &"A", None), 0);
+        assert_eq!(graph.edges(&"A"), vec![("B",Some(11)),("C",Some(5)),("B",Some(10))]);
+        assert!(graph.edges(&"B").is_empty());
+        assert_eq!(graph.phantom_count(), 2);
+    }
+}
@@ -0,0 +1,242 @@
+//! In-place access to a single vertex, for updating it without re-specifying its edges.
+//!
+//! An entry holds the graph lock until dropped, so the vertex cannot change underneath it, and the graph must not be
+//! used through other handles in the meantime.
+
+use std::sync::{Arc,MutexGuard};
+
+use super::{DependencyGraph,Edge,Vertex,VertexState,VertexVec};
+use limits::{self,Limits,LimitError};
+
+/// A view into a single vertex, as returned by `DependencyGraph::entry`
+pub enum Entry<'a,K,V,E> {
+    /// The vertex is resident
+    Occupied(OccupiedEntry<'a,K,V,E>),
+    /// The vertex is absent, or a phantom
+    Vacant(VacantEntry<'a,K,V,E>),
+}
+
+pub struct OccupiedEntry<'a,K,V,E> {
+    key: K,
+    vertex: Arc<Vertex<K,V,E>>,
+    /// Only released early by a panicking `add_edges`, so as not to poison the graph lock
+    vertex_vec: Option<MutexGuard<'a,VertexVec<K,V,E>>>,
+    limits: Limits,
+}
+
+pub struct VacantEntry<'a,K,V,E> {
+    key: K,
+    vertex_vec: MutexGuard<'a,VertexVec<K,V,E>>,
+    limits: Limits,
+}
+
+impl<K,V,E> DependencyGraph<K,V,E> {
+    /// The entry for `key`, locking the graph until it is dropped
+    pub fn entry(&mut self, key: K) -> Entry<'_,K,V,E> where K: PartialEq {
+        let vertex_vec = self.vertex_vec.lock().unwrap();
+        match Vertex::find(&key, &vertex_vec).filter(|vertex| vertex.is_resident()).cloned() {
+            Some(vertex) => Entry::Occupied(OccupiedEntry{ key, vertex, vertex_vec: Some(vertex_vec), limits: self.limits }),
+            None => Entry::Vacant(VacantEntry{ key, vertex_vec, limits: self.limits }),
+        }
+    }
+
+    /// The value for `key`, first inserting the result of `value` with `edge_tuples` if it is absent or a phantom.
+    /// `value` is not called, and the edges are discarded, if it is already resident.
+    pub fn get_or_insert_with<F>(&mut self, key: K, value: F, edge_tuples: Vec<(K,Option<E>)>) -> V
+        where K: PartialEq+Clone, V: Clone, F: FnOnce() -> V {
+        self.entry(key).or_insert_with(value, edge_tuples).get_with(V::clone)
+    }
+}
+
+impl<'a,K,V,E> Entry<'a,K,V,E> {
+    pub fn key(&self) -> &K {
+        match *self {
+            Entry::Occupied(ref entry) => &entry.key,
+            Entry::Vacant(ref entry) => &entry.key,
+        }
+    }
+
+    /// Insert `value` and edges if vacant, otherwise leaving the vertex as it is
+    pub fn or_insert(self, value: V, edge_tuples: Vec<(K,Option<E>)>) -> OccupiedEntry<'a,K,V,E> where K: PartialEq+Clone {
+        self.or_insert_with(|| value, edge_tuples)
+    }
+
+    /// As `or_insert`, only calling `value` if vacant
+    pub fn or_insert_with<F>(self, value: F, edge_tuples: Vec<(K,Option<E>)>) -> OccupiedEntry<'a,K,V,E>
+        where K: PartialEq+Clone, F: FnOnce() -> V {
+        match self {
+            Entry::Occupied(entry) => entry,
+            Entry::Vacant(entry) => entry.insert(value(), edge_tuples),
+        }
+    }
+
+    /// Mutate the value in place if occupied
+    pub fn and_modify<F>(mut self, f: F) -> Self where F: FnOnce(&mut V) {
+        if let Entry::Occupied(ref mut entry) = self {
+            entry.with_value_mut(f);
+        }
+        self
+    }
+}
+
+impl<'a,K,V,E> OccupiedEntry<'a,K,V,E> {
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    /// Call `f` with the value. As the vertex is locked meanwhile, `f` must not call back into the graph.
+    pub fn get_with<R,F>(&self, f: F) -> R where F: FnOnce(&V) -> R {
+        match *self.vertex.state.lock().unwrap() {
+            VertexState::Resident{ ref value, .. } => f(value),
+            VertexState::Phantom => unreachable!(),
+        }
+    }
+
+    /// Mutate the value in place, under the same restriction as `get_with`
+    pub fn with_value_mut<R,F>(&mut self, f: F) -> R where F: FnOnce(&mut V) -> R {
+        match *self.vertex.state.lock().unwrap() {
+            VertexState::Resident{ ref mut value, .. } => f(value),
+            VertexState::Phantom => unreachable!(),
+        }
+    }
+
+    /// Replace the value, leaving the edges untouched, and return the previous value
+    pub fn insert(&mut self, value: V) -> V {
+        self.with_value_mut(|v| std::mem::replace(v, value))
+    }
+
+    /// Append edges to those the vertex already has. Dependencies which are not already inserted are created as phantoms.
+    ///
+    /// Panics if the new edges would exceed the graph's `Limits`, counting those the vertex already has. The graph lock
+    /// is released first, so the graph remains usable, but the entry is not.
+    pub fn add_edges(&mut self, edge_tuples: Vec<(K,Option<E>)>) where K: PartialEq {
+        if let Err(error) = self.try_add_edges(edge_tuples) {
+            drop(self.vertex_vec.take());
+            limits::exceeded(error);
+        }
+    }
+
+    /// As `add_edges`, returning an error rather than panicking, in which case no edges are added
+    pub fn try_add_edges(&mut self, edge_tuples: Vec<(K,Option<E>)>) -> Result<(),LimitError> where K: PartialEq {
+        let existing = match *self.vertex.state.lock().unwrap() {
+            VertexState::Resident{ ref edges, .. } => edges.len(),
+            VertexState::Phantom => unreachable!(),
+        };
+        let vertex_vec = self.vertex_vec.as_mut().expect("entry used after a failed add_edges");
+        self.limits.check_appended(vertex_vec, &self.key, existing, &edge_tuples)?;
+        let added: Vec<Edge<K,V,E>> = edge_tuples.into_iter().map(|(k,m)| Edge::new(k, m, None, vertex_vec)).collect();
+        if let VertexState::Resident{ ref mut edges, .. } = *self.vertex.state.lock().unwrap() {
+            edges.extend(added);
+        }
+        Ok(())
+    }
+
+    /// Remove the vertex, as `DependencyGraph::take`, returning its value
+    pub fn remove(self) -> V where K: Clone {
+        let (_, value, edges) = self.vertex.vacate().expect("occupied entries are resident");
+        drop(edges);
+        value
+    }
+}
+
+impl<'a,K,V,E> VacantEntry<'a,K,V,E> {
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    /// Insert `value` and edges, as `DependencyGraph::insert`. Panics if they would exceed the graph's `Limits`, once
+    /// the graph lock is released.
+    pub fn insert(self, value: V, edge_tuples: Vec<(K,Option<E>)>) -> OccupiedEntry<'a,K,V,E> where K: PartialEq+Clone {
+        match self.try_insert(value, edge_tuples) {
+            Ok(entry) => entry,
+            Err(error) => limits::exceeded(error),
+        }
+    }
+
+    /// As `insert`, returning an error rather than panicking, in which case nothing is inserted and the entry is released
+    pub fn try_insert(mut self, value: V, edge_tuples: Vec<(K,Option<E>)>) -> Result<OccupiedEntry<'a,K,V,E>,LimitError>
+        where K: PartialEq+Clone {
+        self.limits.check(&self.vertex_vec, &self.key, &edge_tuples)?;
+        let (_, released) = self.vertex_vec.insert(self.key.clone(), value, edge_tuples, None);
+        drop(released);
+        let vertex = Vertex::find(&self.key, &self.vertex_vec).expect("just inserted").clone();
+        Ok(OccupiedEntry{
+            key: self.key,
+            vertex,
+            vertex_vec: Some(self.vertex_vec),
+            limits: self.limits,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::panic::{self,AssertUnwindSafe};
+    use super::Entry;
+    use super::super::DependencyGraph;
+    use limits::{Limits,LimitError};
+
+    #[test]
+    fn entry() {
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+        graph.insert("A", 1, vec![]);
+        graph.insert("B", 2, vec![("A",None)]);
+
+        let mut entry = graph.entry("B").and_modify(|v| *v += 10).or_insert(0, vec![]);
+        entry.add_edges(vec![("C",None)]);
+        assert_eq!(entry.insert(20), 12);
+        drop(entry);
+        assert_eq!(graph.get(&"B"), Some(20));
+        assert_eq!(graph.distance_map(&"B").len(), 3, "the edge to A is retained");
+
+        match graph.entry("C") {
+            Entry::Vacant(entry) => assert_eq!(entry.insert(3, vec![("A",None)]).get_with(|v| *v), 3),
+            Entry::Occupied(_) => panic!("C is only a phantom"),
+        }
+        assert_eq!(graph.entry("C").or_insert_with(|| unreachable!(), vec![]).remove(), 3);
+        assert!(!graph.contains_resident(&"C"));
+        assert_eq!(graph.len(), 2);
+    }
+
+    #[test]
+    fn get_or_insert_with() {
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+        graph.insert("B", 2, vec![("A",None)]);
+
+        assert_eq!(graph.get_or_insert_with("B", || unreachable!(), vec![("Z",None)]), 2);
+        assert!(!graph.contains_key(&"Z"));
+        assert_eq!(graph.get_or_insert_with("A", || 1, vec![]), 1);
+        assert_eq!(graph.len(), 2);
+    }
+
+    #[test]
+    fn add_edges_limit() {
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::with_limits(Limits{
+            max_edges_per_vertex: Some(2),
+            ..Limits::default()
+        });
+        graph.insert("A", 1, vec![("X",None)]);
+
+        let failed = panic::catch_unwind(AssertUnwindSafe(|| {
+            let mut entry = graph.entry("A").or_insert(0, vec![]);
+            entry.add_edges(vec![("Y",None)]);
+            // Too many alongside the two it already has, even though one alone is within the limit
+            entry.add_edges(vec![("Z",None)]);
+        }));
+        assert!(failed.unwrap_err().downcast::<String>().unwrap().contains("EdgesPerVertex"));
+
+        // The graph lock was released unpoisoned, and the failed edge not added
+        assert_eq!(graph.edges(&"A"), vec![("X",None),("Y",None)]);
+        let mut entry = graph.entry("A").or_insert(0, vec![]);
+        assert_eq!(entry.try_add_edges(vec![("Z",None)]), Err(LimitError::EdgesPerVertex{ limit: 2 }));
+        drop(entry);
+        match graph.entry("B") {
+            Entry::Vacant(entry) => assert!(entry.try_insert(2, vec![("X",None),("Y",None),("Z",None)]).is_err()),
+            Entry::Occupied(_) => panic!("B was never inserted"),
+        }
+        assert!(!graph.contains_key(&"B"));
+        assert!(!graph.contains_key(&"Z"));
+        graph.insert("B", 2, vec![("A",None)]);
+        assert_eq!(graph.len(), 2);
+    }
+}
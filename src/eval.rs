@@ -0,0 +1,326 @@
+//! Dataflow-style evaluation over the graph in topological order.
+
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{BuildHasher,BuildHasherDefault,Hash,Hasher};
+use std::sync::MutexGuard;
+
+use super::{DependencyGraph,VertexState};
+use adjacency::Adjacency;
+use lazy::Loader;
+
+/// Results of a previous `eval_cached`, keyed on a hash of each vertex's value and everything upstream of it.
+///
+/// Hashes are computed with `S`. The default, `DefaultHasher`, isn't guaranteed to hash alike across Rust releases, so
+/// caches which are persisted should be given a stable hasher with `with_hasher`.
+pub struct EvalCache<K,R,S=BuildHasherDefault<DefaultHasher>> {
+    entries: HashMap<K,(u64,R)>,
+    hasher: S,
+    /// Consulted for results not held in memory, typically those persisted from `entries` by a previous run
+    store: Option<Box<dyn Loader<K,(u64,R)> + Send + Sync>>,
+}
+
+impl<K,R> EvalCache<K,R> where K: Eq+Hash {
+    pub fn new() -> Self {
+        EvalCache::with_hasher(BuildHasherDefault::default())
+    }
+}
+
+impl<K,R,S> EvalCache<K,R,S> where K: Eq+Hash {
+    pub fn with_hasher(hasher: S) -> Self {
+        EvalCache {
+            entries: HashMap::new(),
+            hasher,
+            store: None,
+        }
+    }
+    /// Fall back on `store` for the hash and result of any vertex not already cached in memory. The result is used
+    /// only if the hash still matches, as for those in memory.
+    pub fn with_store<L>(mut self, store: L) -> Self where L: Loader<K,(u64,R)> + Send + Sync + 'static {
+        self.store = Some(Box::new(store));
+        self
+    }
+    /// Each cached key, with the hash and result recorded for it, to be persisted and later loaded through `with_store`
+    pub fn entries(&self) -> impl Iterator<Item=(&K,u64,&R)> {
+        self.entries.iter().map(|(key, (hash, result))| (key, *hash, result))
+    }
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+    pub fn clear(&mut self) {
+        self.entries.clear()
+    }
+}
+
+impl<K,R> Default for EvalCache<K,R> where K: Eq+Hash {
+    fn default() -> Self {
+        EvalCache::new()
+    }
+}
+
+impl<K,V,E> DependencyGraph<K,V,E> {
+    /// Visit each resident vertex in topological order, with mutable access to its value and read access to
+    /// the values of its dependencies which have already been visited (in edge order). Phantom dependencies and,
+    /// within a cycle, dependencies not yet visited are omitted.
+    ///
+    /// The graph is locked for the duration of the visit, so `visit` must not call back into it.
+    pub fn visit_mut_topo<F>(&self, mut visit: F)
+        where F: FnMut(&K, &mut V, &[&V]) {
+        let vertex_vec = self.vertex_vec.lock().unwrap();
+        let adjacency = Adjacency::new(&vertex_vec);
+        let mut visited = vec![false; vertex_vec.len()];
+
+        for i in adjacency.topo_order() {
+            let key = vertex_vec[i].key.lock().unwrap();
+            let mut state = vertex_vec[i].state.lock().unwrap();
+
+            // Lock each distinct dependency once. Self-edges are already covered by `state`
+            let dep_states: Vec<MutexGuard<VertexState<K,V,E>>> = adjacency.dependencies(i).into_iter()
+                .filter(|&j| visited[j])
+                .map(|j| vertex_vec[j].state.lock().unwrap())
+                .collect();
+            let dep_values: Vec<&V> = dep_states.iter().filter_map(|s| {
+                match **s {
+                    VertexState::Resident{ ref value, .. } => Some(value),
+                    VertexState::Phantom => None,
+                }
+            }).collect();
+
+            if let (Some(key), &mut VertexState::Resident{ ref mut value, .. }) = (key.as_ref(), &mut *state) {
+                visit(key, value, &dep_values);
+            }
+            visited[i] = true;
+        }
+    }
+
+    /// As `visit_mut_topo`, without access to the values of dependencies, and so locking only one vertex at a time
+    pub fn for_each_mut_topo<F>(&self, mut f: F) where F: FnMut(&K, &mut V) {
+        let vertex_vec = self.vertex_vec.lock().unwrap();
+        for i in Adjacency::new(&vertex_vec).topo_order() {
+            let key = vertex_vec[i].key.lock().unwrap();
+            let mut state = vertex_vec[i].state.lock().unwrap();
+            if let (Some(key), &mut VertexState::Resident{ ref mut value, .. }) = (key.as_ref(), &mut *state) {
+                f(key, value);
+            }
+        }
+    }
+
+    /// Compute a result for each resident vertex from its value and the results of its dependencies, in topological order.
+    /// As with `visit_mut_topo`, phantom dependencies and not-yet-evaluated cycle members contribute no result.
+    ///
+    /// The graph is locked for the duration of the evaluation, so `eval` must not call back into it.
+    pub fn eval<R,F>(&self, mut eval: F) -> HashMap<K,R>
+        where K: Eq+Hash+Clone, F: FnMut(&K, &V, Vec<&R>) -> R {
+        let vertex_vec = self.vertex_vec.lock().unwrap();
+        let adjacency = Adjacency::new(&vertex_vec);
+        let mut results: Vec<Option<R>> = (0..vertex_vec.len()).map(|_| None).collect();
+
+        for i in adjacency.topo_order() {
+            let result = {
+                let key = vertex_vec[i].key.lock().unwrap();
+                let state = vertex_vec[i].state.lock().unwrap();
+                let dep_results: Vec<&R> = adjacency.dependencies(i).into_iter().filter_map(|j| results[j].as_ref()).collect();
+
+                match (key.as_ref(), &*state) {
+                    (Some(key), VertexState::Resident{ value, .. }) => Some(eval(key, value, dep_results)),
+                    _ => None,
+                }
+            };
+            results[i] = result;
+        }
+
+        vertex_vec.iter().zip(results).filter_map(|(vertex, result)| {
+            match (vertex.key.lock().unwrap().as_ref(), result) {
+                (Some(key), Some(result)) => Some((key.clone(), result)),
+                _ => None,
+            }
+        }).collect()
+    }
+
+    /// As `eval`, but skipping `eval` for any vertex whose value and upstream values are unchanged since the result in `cache`
+    /// was computed. The cache is updated with the new results, and entries for vertices no longer resident are dropped.
+    pub fn eval_cached<R,S,F>(&self, cache: &mut EvalCache<K,R,S>, mut eval: F) -> HashMap<K,R>
+        where K: Eq+Hash+Clone, V: Hash, R: Clone, S: BuildHasher, F: FnMut(&K, &V, Vec<&R>) -> R {
+        let vertex_vec = self.vertex_vec.lock().unwrap();
+        let adjacency = Adjacency::new(&vertex_vec);
+        let mut hashes: Vec<Option<u64>> = vec![None; vertex_vec.len()];
+        let mut results: Vec<Option<R>> = (0..vertex_vec.len()).map(|_| None).collect();
+        let mut entries = HashMap::new();
+
+        for i in adjacency.topo_order() {
+            let key = vertex_vec[i].key.lock().unwrap();
+            let state = vertex_vec[i].state.lock().unwrap();
+            let (key, value) = match (key.as_ref(), &*state) {
+                (Some(key), VertexState::Resident{ value, .. }) => (key, value),
+                _ => continue,
+            };
+
+            let deps = adjacency.dependencies(i);
+            let mut hasher = cache.hasher.build_hasher();
+            value.hash(&mut hasher);
+            for &j in deps.iter() {
+                // Phantoms hash as None, so resolving one invalidates its dependents
+                hashes[j].hash(&mut hasher);
+            }
+            let hash = hasher.finish();
+
+            let cached = cache.entries.remove(key).or_else(|| cache.store.as_ref()?.load(key));
+            let result = match cached {
+                Some((cached_hash, result)) if cached_hash == hash => result,
+                _ => {
+                    let dep_results: Vec<&R> = deps.into_iter().filter_map(|j| results[j].as_ref()).collect();
+                    eval(key, value, dep_results)
+                }
+            };
+
+            entries.insert(key.clone(), (hash, result.clone()));
+            hashes[i] = Some(hash);
+            results[i] = Some(result);
+        }
+        cache.entries = entries;
+
+        vertex_vec.iter().zip(results).filter_map(|(vertex, result)| {
+            match (vertex.key.lock().unwrap().as_ref(), result) {
+                (Some(key), Some(result)) => Some((key.clone(), result)),
+                _ => None,
+            }
+        }).collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use std::hash::{BuildHasherDefault,Hasher};
+    use super::EvalCache;
+    use super::super::DependencyGraph;
+
+    #[test]
+    fn visit_mut_topo() {
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+        graph.insert("C", 100, vec![("A",None),("B",None)]);
+        graph.insert("B", 10,  vec![("A",None),("Z",None)]);
+        graph.insert("A", 1,   vec![]);
+
+        let mut order = Vec::new();
+        graph.visit_mut_topo(|key, value, deps| {
+            order.push(*key);
+            *value += deps.iter().map(|d| **d).sum::<i32>();
+        });
+        assert_eq!(order, vec!["A","B","C"]);
+
+        let mut values = Vec::new();
+        graph.visit_mut_topo(|key, value, _| values.push((*key, *value)));
+        assert_eq!(values, vec![("A",1),("B",11),("C",112)]);
+    }
+
+    #[test]
+    fn for_each_mut_topo() {
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+        graph.insert("B", "Bravo".to_string(), vec![("A",None)]);
+        graph.insert("A", "Alpha".to_string(), vec![]);
+
+        let mut order = Vec::new();
+        graph.for_each_mut_topo(|key, value| {
+            order.push(*key);
+            value.make_ascii_uppercase();
+        });
+        assert_eq!(order, vec!["A","B"]);
+        assert_eq!(graph.get(&"B"), Some("BRAVO".to_string()));
+    }
+
+    #[test]
+    fn eval() {
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+        graph.insert("C", 3, vec![("A",None),("B",None)]);
+        graph.insert("B", 2, vec![("A",None),("Z",None)]);
+        graph.insert("A", 1, vec![]);
+
+        // Longest chain of dependencies beneath each vertex, weighted by value
+        let depths = graph.eval(|_, value, deps: Vec<&i32>| value + deps.into_iter().max().cloned().unwrap_or(0));
+
+        assert_eq!(depths.len(), 3);
+        assert_eq!(depths["A"], 1);
+        assert_eq!(depths["B"], 3);
+        assert_eq!(depths["C"], 6);
+    }
+
+    #[test]
+    fn eval_cached() {
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+        graph.insert("C", 3, vec![("A",None),("B",None)]);
+        graph.insert("B", 2, vec![("Z",None)]);
+        graph.insert("A", 1, vec![]);
+
+        let mut cache = EvalCache::new();
+        let mut evaluated = Vec::new();
+        let sums = graph.eval_cached(&mut cache, |key, value, deps: Vec<&i32>| {
+            evaluated.push(*key);
+            value + deps.into_iter().sum::<i32>()
+        });
+        assert_eq!(sums["C"], 6);
+        assert_eq!(evaluated.len(), 3);
+        assert_eq!(cache.len(), 3);
+
+        // Nothing changed
+        evaluated.clear();
+        let sums = graph.eval_cached(&mut cache, |key, value, deps: Vec<&i32>| {
+            evaluated.push(*key);
+            value + deps.into_iter().sum::<i32>()
+        });
+        assert_eq!(sums["C"], 6);
+        assert!(evaluated.is_empty());
+
+        // Resolving the phantom invalidates B and, transitively, C but not A
+        graph.insert("Z", 10, vec![]);
+        evaluated.clear();
+        let sums = graph.eval_cached(&mut cache, |key, value, deps: Vec<&i32>| {
+            evaluated.push(*key);
+            value + deps.into_iter().sum::<i32>()
+        });
+        assert_eq!(sums["C"], 16);
+        assert_eq!(evaluated, vec!["Z","B","C"]);
+    }
+
+    /// FNV-1a, which unlike `DefaultHasher` hashes alike from one build to the next
+    #[derive(Clone,Copy,Default)]
+    struct Fnv(u64);
+    impl Hasher for Fnv {
+        fn finish(&self) -> u64 {
+            self.0
+        }
+        fn write(&mut self, bytes: &[u8]) {
+            for &b in bytes {
+                self.0 = (self.0 ^ b as u64).wrapping_mul(0x100000001b3);
+            }
+        }
+    }
+
+    #[test]
+    fn eval_cache_store() {
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+        graph.insert("B", 2, vec![("A",None)]);
+        graph.insert("A", 1, vec![]);
+        let sum = |_: &&str, value: &i32, deps: Vec<&i32>| value + deps.into_iter().sum::<i32>();
+
+        let hasher = BuildHasherDefault::<Fnv>::default();
+        let mut cache = EvalCache::with_hasher(hasher.clone());
+        graph.eval_cached(&mut cache, sum);
+        let persisted: HashMap<&str,(u64,i32)> = cache.entries().map(|(k, hash, r)| (*k, (hash, *r))).collect();
+        assert_eq!(persisted.len(), 2);
+
+        // A fresh cache, as in a later run, loads what it can from the store
+        let mut cache = EvalCache::with_hasher(hasher).with_store(move |k: &&str| persisted.get(k).cloned());
+        graph.insert("B", 20, vec![("A",None)]);
+        let mut evaluated = Vec::new();
+        let sums = graph.eval_cached(&mut cache, |key, value, deps| {
+            evaluated.push(*key);
+            sum(key, value, deps)
+        });
+        assert_eq!(evaluated, vec!["B"]);
+        assert_eq!(sums["B"], 21);
+    }
+}
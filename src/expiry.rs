@@ -0,0 +1,98 @@
+//! Time-limited edges, removed by a sweep once their expiry passes.
+//!
+//! Timestamps are plain `u64`s in whatever units the caller chooses, so long as `expire_edges` is given the same.
+
+use std::borrow::Borrow;
+use std::sync::Arc;
+
+use super::{DependencyGraph,Edge,EdgeMeta,InsertOutcome,Vertex,VertexState};
+
+impl<K,V,E> DependencyGraph<K,V,E> {
+    /// As `insert`, with each of the edges created expiring at `expires`
+    pub fn insert_expiring(&mut self, key: K, value: V, edge_tuples: Vec<(K,Option<E>)>, expires: u64)
+        -> InsertOutcome<K,V,E> where K: PartialEq+Clone {
+        let meta = EdgeMeta{ expires: Some(expires), ..EdgeMeta::default() };
+        self.insert_attributed(key, value, edge_tuples, Some(&meta), InsertOutcome::keyed)
+    }
+
+    /// Set or clear the expiry of every edge from `from` to `to`, returning the number of edges updated
+    pub fn set_edge_expiry<Q>(&self, from: &Q, to: &Q, expires: Option<u64>) -> usize where K: Borrow<Q>, Q: ?Sized+PartialEq {
+        let vertex_vec = self.vertex_vec.lock().unwrap();
+        let vertex = match Vertex::find(from, &vertex_vec) {
+            Some(vertex) => vertex,
+            None => return 0,
+        };
+        let mut state = vertex.state.lock().unwrap();
+        let mut updated = 0;
+        if let VertexState::Resident{ ref mut edges, .. } = *state {
+            for edge in edges.iter_mut() {
+                if edge.dest_vertex.key.lock().unwrap().as_ref().map(Borrow::borrow) == Some(to) {
+                    edge.meta.get_or_insert_with(Box::default).expires = expires;
+                    updated += 1;
+                }
+            }
+        }
+        updated
+    }
+
+    /// Remove every edge whose expiry is at or before `now`, returning each as `(from, to)`. The edges of pinned vertices
+    /// are kept regardless. Phantoms referenced only by expired edges are removed with them.
+    pub fn expire_edges(&mut self, now: u64) -> Vec<(K,K)> where K: Clone {
+        let vertex_vec = self.vertex_vec.lock().unwrap();
+        let mut expired: Vec<(K,Edge<K,V,E>)> = Vec::new();
+
+        for vertex in vertex_vec.iter().map(Arc::as_ref) {
+            let key = match *vertex.key.lock().unwrap() {
+                Some(ref key) => key.clone(),
+                None => continue,
+            };
+            if *vertex.pinned.lock().unwrap() {
+                continue;
+            }
+            let mut state = vertex.state.lock().unwrap();
+            if let VertexState::Resident{ ref mut edges, .. } = *state {
+                let mut i = 0;
+                while i < edges.len() {
+                    if edges[i].meta.as_ref().and_then(|m| m.expires).is_some_and(|at| at <= now) {
+                        expired.push((key.clone(), edges.remove(i)));
+                    }else{
+                        i += 1;
+                    }
+                }
+            }
+        }
+
+        // Dropped only now that no state lock is held, as an edge may point back at its own vertex
+        expired.into_iter().filter_map(|(from, edge)| {
+            let to = edge.dest_vertex.key.lock().unwrap().clone()?;
+            Some((from, to))
+        }).collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::DependencyGraph;
+
+    #[test]
+    fn expire_edges() {
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+        graph.insert("A", "Alpha", vec![]);
+        graph.insert_expiring("B", "Bravo", vec![("A",None),("Z",None)], 100);
+        graph.insert("C", "Charlie", vec![("A",None),("B",None)]);
+        assert_eq!(graph.set_edge_expiry(&"C", &"A", Some(200)), 1);
+        assert_eq!(graph.set_edge_expiry(&"C", &"Y", Some(200)), 0);
+
+        assert!(graph.expire_edges(99).is_empty());
+
+        let mut expired = graph.expire_edges(100);
+        expired.sort();
+        assert_eq!(expired, vec![("B","A"),("B","Z")]);
+        assert_eq!(graph.metrics().phantoms, 0, "Z was only referenced by an expired edge");
+        assert_eq!(graph.distance_map(&"B").len(), 1);
+
+        assert_eq!(graph.set_edge_expiry(&"C", &"A", None), 1);
+        assert!(graph.expire_edges(1000).is_empty());
+        assert_eq!(graph.distance_map(&"C").len(), 3);
+    }
+}
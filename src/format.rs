@@ -0,0 +1,177 @@
+//! Textual renderings of a graph, for diagnostics and logs.
+
+use std::fmt;
+
+use super::{DependencyGraph,Edge,Vertex,VertexState};
+use adjacency::Adjacency;
+
+/// An adjacency list rendering of a graph, one `A -> [B, C(2), Z?]` line per resident vertex in topological order.
+/// Measures are shown in parentheses, and phantoms suffixed with `?`. Returned by `DependencyGraph::pretty`.
+/// Properties, if shown, follow the key as DOT-style attributes: `A [color="red", weight=3] -> [B]`.
+pub struct Pretty<'a,K: 'a,V: 'a,E: 'a> {
+    graph: &'a DependencyGraph<K,V,E>,
+    measures: bool,
+    refcounts: bool,
+    properties: bool,
+}
+
+impl<K,V,E> DependencyGraph<K,V,E> {
+    /// Render the graph as an adjacency list, showing measures but not refcounts. This is also its `Display`.
+    pub fn pretty(&self) -> Pretty<'_,K,V,E> {
+        Pretty{
+            graph: self,
+            measures: true,
+            refcounts: false,
+            properties: false,
+        }
+    }
+}
+
+impl<'a,K,V,E> Pretty<'a,K,V,E> {
+    pub fn measures(mut self, show: bool) -> Self {
+        self.measures = show;
+        self
+    }
+    /// Show each resident vertex's refcount after its key, as `A#2`
+    pub fn refcounts(mut self, show: bool) -> Self {
+        self.refcounts = show;
+        self
+    }
+    /// Show each resident vertex's properties, if it has any
+    pub fn properties(mut self, show: bool) -> Self {
+        self.properties = show;
+        self
+    }
+}
+
+impl<'a,K,V,E> fmt::Display for Pretty<'a,K,V,E> where K: fmt::Display, E: fmt::Display {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let vertex_vec = self.graph.vertex_vec.lock().unwrap();
+        let adjacency = Adjacency::new(&vertex_vec);
+        for i in adjacency.topo_order_resident() {
+            let vertex = &vertex_vec[i];
+            if let Some(ref key) = *vertex.key.lock().unwrap() {
+                write!(f, "{}", key)?;
+            }
+            if self.refcounts {
+                write!(f, "#{}", vertex.refcount.lock().unwrap())?;
+            }
+            let properties = vertex.properties.lock().unwrap();
+            if self.properties && !properties.is_empty() {
+                write!(f, " [")?;
+                for (n, (name, value)) in properties.iter().enumerate() {
+                    if n > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}={}", name, value)?;
+                }
+                write!(f, "]")?;
+            }
+            drop(properties);
+            write!(f, " -> [")?;
+            if let VertexState::Resident{ ref edges, .. } = *vertex.state.lock().unwrap() {
+                // Edges and `out` share an order. A self-edge's key is unlocked by now
+                for (n, (edge, &j)) in edges.iter().zip(adjacency.out[i].iter()).enumerate() {
+                    if n > 0 {
+                        write!(f, ", ")?;
+                    }
+                    if let Some(ref key) = *edge.dest_vertex.key.lock().unwrap() {
+                        write!(f, "{}", key)?;
+                    }
+                    match edge.measure {
+                        Some(ref measure) if self.measures => write!(f, "({})", measure)?,
+                        _ => {},
+                    }
+                    if !adjacency.resident[j] {
+                        write!(f, "?")?;
+                    }
+                }
+            }
+            writeln!(f, "]")?;
+        }
+        Ok(())
+    }
+}
+
+impl<K,V,E> fmt::Display for DependencyGraph<K,V,E> where K: fmt::Display, E: fmt::Display {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.pretty().fmt(f)
+    }
+}
+
+/// Shows every vertex by key, with its status, refcount, and (if resident) its value and edges, in storage order
+impl<K,V,E> fmt::Debug for DependencyGraph<K,V,E> where K: fmt::Debug, V: fmt::Debug, E: fmt::Debug {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let vertex_vec = self.vertex_vec.lock().unwrap();
+        let mut map = f.debug_map();
+        for vertex in vertex_vec.iter() {
+            // The key lock is released before the vertex is shown, as a self-edge shows it again
+            match *vertex.key.lock().unwrap() {
+                Some(ref key) => map.key(key),
+                None => continue,
+            };
+            map.value(&DebugVertex(vertex));
+        }
+        map.finish()
+    }
+}
+
+struct DebugVertex<'a,K: 'a,V: 'a,E: 'a>(&'a Vertex<K,V,E>);
+struct DebugEdge<'a,K: 'a,V: 'a,E: 'a>(&'a Edge<K,V,E>);
+
+impl<'a,K,V,E> fmt::Debug for DebugVertex<'a,K,V,E> where K: fmt::Debug, V: fmt::Debug, E: fmt::Debug {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let status = format!("{:?}", self.0.status());
+        let refcount = *self.0.refcount.lock().unwrap();
+        match *self.0.state.lock().unwrap() {
+            VertexState::Resident{ ref value, ref edges } => {
+                let edges: Vec<DebugEdge<K,V,E>> = edges.iter().map(DebugEdge).collect();
+                f.debug_struct(&status).field("refcount", &refcount).field("value", value).field("edges", &edges).finish()
+            },
+            VertexState::Phantom => f.debug_struct(&status).field("refcount", &refcount).finish(),
+        }
+    }
+}
+
+impl<'a,K,V,E> fmt::Debug for DebugEdge<'a,K,V,E> where K: fmt::Debug, E: fmt::Debug {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut tuple = f.debug_tuple("");
+        // Edges keep their destination keyed
+        if let Some(ref key) = *self.0.dest_vertex.key.lock().unwrap() {
+            tuple.field(key);
+        }
+        tuple.field(&self.0.measure).finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::DependencyGraph;
+
+    #[test]
+    fn debug() {
+        let mut graph = DependencyGraph::new();
+        graph.insert("A", "Alpha", vec![("A",Some(1)),("Z",None)]);
+        graph.mask(&"A");
+
+        assert_eq!(format!("{:?}", graph), concat!(
+            r#"{"A": Masked { refcount: 2, value: "Alpha", edges: [("A", Some(1)), ("Z", None)] }, "#,
+            r#""Z": Phantom { refcount: 1 }}"#,
+        ));
+    }
+
+    #[test]
+    fn pretty() {
+        let mut graph = DependencyGraph::new();
+        graph.insert("A", "Alpha", vec![]);
+        graph.insert("B", "Bravo", vec![("A",Some(2)),("Z",None),("B",Some(1))]);
+
+        assert_eq!(graph.to_string(), "A -> []\nB -> [A(2), Z?, B(1)]\n");
+        assert_eq!(graph.pretty().measures(false).refcounts(true).to_string(), "A#2 -> []\nB#2 -> [A, Z?, B]\n");
+
+        graph.set_property(&"B", "label", "Bravo \"B\"");
+        graph.set_property(&"B", "weight", 3);
+        assert_eq!(graph.pretty().measures(false).properties(true).to_string(),
+            "A -> []\nB [label=\"Bravo \\\"B\\\"\", weight=3] -> [A, Z?, B]\n");
+    }
+}
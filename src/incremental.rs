@@ -0,0 +1,153 @@
+//! A small incremental computation engine built atop `DependencyGraph`.
+//!
+//! Inputs are set directly, while derived vertices are computed from their dependencies by a registered function.
+//! Changing an input marks it dirty, and `recompute` re-evaluates only the vertices downstream of a change.
+
+use std::sync::Arc;
+
+use super::DependencyGraph;
+
+/// Shared between clones of a node, and bound like the rest of the graph so that an `Engine` can cross threads
+type Rule<V> = Arc<dyn Fn(&[&V]) -> V + Send + Sync>;
+
+#[derive(Clone)]
+struct Node<V> {
+    value: Option<V>,
+    rule: Option<Rule<V>>,
+    /// Number of distinct dependencies the rule expects
+    arity: usize,
+    dirty: bool,
+    /// Whether the value changed during the most recent recompute
+    changed: bool,
+}
+
+pub struct Engine<K,V> {
+    graph: DependencyGraph<K,Node<V>,()>,
+}
+
+impl<K,V> Default for Engine<K,V> where K: PartialEq, V: Clone {
+    fn default() -> Self {
+        Engine::new()
+    }
+}
+
+impl<K,V> Engine<K,V> where K: PartialEq, V: Clone {
+    pub fn new() -> Self {
+        Engine {
+            graph: DependencyGraph::new(),
+        }
+    }
+
+    /// Set the value of an input vertex, marking it dirty. Any rule previously defined for this key is discarded.
+    pub fn set_input(&mut self, key: K, value: V) {
+        self.graph.insert_attributed(key, Node {
+            value: Some(value),
+            rule: None,
+            arity: 0,
+            dirty: true,
+            changed: false,
+        }, vec![], None, drop);
+    }
+
+    /// Define a derived vertex, computed by `compute` from the values of `deps` (in the order given, duplicates removed).
+    /// Dependencies need not be defined yet. The vertex has no value until all of them do and `recompute` is called.
+    pub fn define<F>(&mut self, key: K, mut deps: Vec<K>, compute: F)
+        where F: Fn(&[&V]) -> V + Send + Sync + 'static {
+        let mut distinct: Vec<K> = Vec::with_capacity(deps.len());
+        for dep in deps.drain(..) {
+            if !distinct.contains(&dep) {
+                distinct.push(dep);
+            }
+        }
+
+        let node = Node {
+            value: None,
+            rule: Some(Arc::new(compute)),
+            arity: distinct.len(),
+            dirty: true,
+            changed: false,
+        };
+        self.graph.insert_attributed(key, node, distinct.into_iter().map(|k| (k,None)).collect(), None, drop);
+    }
+
+    /// Re-evaluate every derived vertex which is dirty or downstream of a dirty vertex, in topological order.
+    /// Returns the keys of the derived vertices which were recomputed.
+    pub fn recompute(&mut self) -> Vec<K> where K: Clone {
+        let mut recomputed = Vec::new();
+
+        self.graph.visit_mut_topo(|key, node, deps| {
+            let stale = node.dirty || deps.iter().any(|d| d.changed);
+            node.dirty = false;
+            node.changed = false;
+            if !stale {
+                return;
+            }
+
+            if let Some(ref rule) = node.rule {
+                let dep_values: Vec<&V> = deps.iter().filter_map(|d| d.value.as_ref()).collect();
+                node.value = if dep_values.len() == node.arity {
+                    Some(rule(&dep_values))
+                }else{
+                    // Some dependency is a phantom or has no value yet
+                    None
+                };
+                recomputed.push(key.clone());
+            }
+            node.changed = true;
+        });
+
+        recomputed
+    }
+
+    /// The current value of an input or derived vertex. Derived values are as of the last `recompute`.
+    pub fn get(&self, key: &K) -> Option<V> {
+        self.graph.get_with(key, |node| node.value.clone()).flatten()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Engine;
+
+    #[test]
+    fn recompute_affected() {
+        let mut engine = Engine::new();
+        engine.define("double", vec!["sum"], |d| d[0] * 2);
+        engine.define("sum", vec!["a","b"], |d| d[0] + d[1]);
+        engine.define("tenfold", vec!["b"], |d| d[0] * 10);
+        engine.set_input("b", 2);
+
+        // "a" is not yet defined, so only "tenfold" can be computed
+        let mut recomputed = engine.recompute();
+        recomputed.sort();
+        assert_eq!(recomputed, vec!["double","sum","tenfold"]);
+        assert_eq!(engine.get(&"sum"), None);
+        assert_eq!(engine.get(&"tenfold"), Some(20));
+
+        engine.set_input("a", 1);
+        assert_eq!(engine.recompute(), vec!["sum","double"]);
+        assert_eq!(engine.get(&"double"), Some(6));
+
+        assert_eq!(engine.recompute(), Vec::<&str>::new());
+
+        engine.set_input("a", 5);
+        assert_eq!(engine.recompute(), vec!["sum","double"]);
+        assert_eq!(engine.get(&"sum"), Some(7));
+        assert_eq!(engine.get(&"double"), Some(14));
+        assert_eq!(engine.get(&"tenfold"), Some(20));
+    }
+
+    #[test]
+    fn send() {
+        let mut engine = Engine::new();
+        engine.define("sum", vec!["a","b"], |d| d[0] + d[1]);
+        engine.set_input("a", 1);
+        engine.set_input("b", 2);
+
+        let engine = std::thread::spawn(move || {
+            engine.recompute();
+            engine
+        }).join().unwrap();
+        assert_eq!(engine.get(&"sum"), Some(3));
+    }
+}
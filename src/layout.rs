@@ -0,0 +1,220 @@
+//! Layered (Sugiyama-style) layout, computed as plain data for visualizers to render however they like.
+//!
+//! Vertices are assigned to layers such that every dependency sits in a lower layer than its dependents, edges
+//! spanning several layers are routed through one waypoint per intermediate layer, and the order within each layer
+//! is refined by barycenter sweeps to reduce crossings.
+
+use super::{DependencyGraph,Property};
+use adjacency::Adjacency;
+
+/// Number of down-and-up barycenter sweeps
+const SWEEPS: usize = 4;
+
+#[derive(Clone,Debug,PartialEq)]
+pub struct Layout<K> {
+    /// Every vertex, resident or phantom, in ascending layer then position
+    pub vertices: Vec<VertexLayout<K>>,
+    /// One entry per distinct dependency. Self-edges are omitted
+    pub edges: Vec<EdgeLayout<K>>,
+    /// Number of slots in each layer, including waypoints
+    pub widths: Vec<usize>,
+}
+
+#[derive(Clone,Debug,PartialEq)]
+pub struct VertexLayout<K> {
+    pub key: K,
+    /// Zero for vertices without dependencies
+    pub layer: usize,
+    /// Position within the layer, from zero
+    pub position: usize,
+    pub resident: bool,
+    /// The vertex's properties, for rendering as attributes. Empty for phantoms
+    pub properties: Vec<(String,Property)>,
+}
+
+#[derive(Clone,Debug,PartialEq)]
+pub struct EdgeLayout<K> {
+    /// The dependent
+    pub from: K,
+    /// The dependency
+    pub to: K,
+    /// `(layer, position)` of each intermediate layer the edge crosses, in order from `from` to `to`
+    pub waypoints: Vec<(usize,usize)>,
+    /// Whether the edge had to be drawn upward, from a lower layer to a higher one, in order to break a cycle
+    pub reversed: bool,
+}
+
+impl<K,V,E> DependencyGraph<K,V,E> {
+    /// Compute a layered layout of the graph, as of now.
+    pub fn layout(&self) -> Layout<K> where K: Clone {
+        let vertex_vec = self.vertex_vec.lock().unwrap();
+        let adjacency = Adjacency::new(&vertex_vec);
+        let keys: Vec<Option<K>> = vertex_vec.iter().map(|v| v.key.lock().unwrap().clone()).collect();
+
+        // Phantoms have no dependencies, and so may go first
+        let order: Vec<usize> = (0..keys.len()).filter(|&i| keys[i].is_some() && !adjacency.resident[i])
+            .chain(adjacency.topo_order_resident())
+            .collect();
+        let mut rank = vec![usize::MAX; keys.len()];
+        for (r, &i) in order.iter().enumerate() {
+            rank[i] = r;
+        }
+
+        // Orient each edge from lower to higher rank, which reverses those closing a cycle
+        let mut lower: Vec<Vec<usize>> = vec![Vec::new(); keys.len()];
+        let mut edges = Vec::new();
+        for &i in order.iter() {
+            for j in adjacency.dependencies(i) {
+                if rank[j] < rank[i] {
+                    lower[i].push(j);
+                    edges.push((i, j, false));
+                }else{
+                    lower[j].push(i);
+                    edges.push((i, j, true));
+                }
+            }
+        }
+
+        // Longest path layering
+        let mut layer = vec![0; keys.len()];
+        for &i in order.iter() {
+            layer[i] = lower[i].iter().map(|&j| layer[j] + 1).max().unwrap_or(0);
+        }
+
+        // Nodes of the layered graph are the vertex slots in `order`, followed by one dummy per waypoint
+        let mut node_layer: Vec<usize> = order.iter().map(|&i| layer[i]).collect();
+        let mut node_down: Vec<Vec<usize>> = vec![Vec::new(); order.len()];
+        let mut node_up: Vec<Vec<usize>> = vec![Vec::new(); order.len()];
+        let mut chains: Vec<Vec<usize>> = Vec::with_capacity(edges.len());
+        for &(i, j, reversed) in edges.iter() {
+            let (bottom, top) = if reversed { (rank[i], rank[j]) } else { (rank[j], rank[i]) };
+            // Chain of nodes from the top of the edge down to the bottom
+            let mut chain = vec![top];
+            for l in (node_layer[bottom] + 1 .. node_layer[top]).rev() {
+                node_layer.push(l);
+                node_down.push(Vec::new());
+                node_up.push(Vec::new());
+                chain.push(node_layer.len() - 1);
+            }
+            chain.push(bottom);
+            for pair in chain.windows(2) {
+                node_down[pair[0]].push(pair[1]);
+                node_up[pair[1]].push(pair[0]);
+            }
+            chains.push(chain);
+        }
+
+        let height = node_layer.iter().map(|&l| l + 1).max().unwrap_or(0);
+        let mut rows: Vec<Vec<usize>> = vec![Vec::new(); height];
+        for (n, &l) in node_layer.iter().enumerate() {
+            rows[l].push(n);
+        }
+        let mut position = vec![0; node_layer.len()];
+        let place = |row: &[usize], position: &mut Vec<usize>| {
+            for (p, &n) in row.iter().enumerate() {
+                position[n] = p;
+            }
+        };
+        for row in rows.iter() {
+            place(row, &mut position);
+        }
+
+        for _ in 0..SWEEPS {
+            for row in rows.iter_mut().skip(1) {
+                sort_by_barycenter(row, &node_down, &position);
+                place(row, &mut position);
+            }
+            for row in rows.iter_mut().rev().skip(1) {
+                sort_by_barycenter(row, &node_up, &position);
+                place(row, &mut position);
+            }
+        }
+
+        let mut vertices: Vec<VertexLayout<K>> = order.iter().enumerate().filter_map(|(n, &i)| {
+            Some(VertexLayout{
+                key: keys[i].clone()?,
+                layer: node_layer[n],
+                position: position[n],
+                resident: adjacency.resident[i],
+                properties: vertex_vec[i].properties.lock().unwrap().clone(),
+            })
+        }).collect();
+        vertices.sort_by_key(|v| (v.layer, v.position));
+
+        let edges = edges.into_iter().zip(chains).filter_map(|((i, j, reversed), chain)| {
+            let mut waypoints: Vec<(usize,usize)> = chain[1..chain.len()-1].iter().map(|&n| (node_layer[n], position[n])).collect();
+            if reversed {
+                waypoints.reverse();
+            }
+            Some(EdgeLayout{
+                from: keys[i].clone()?,
+                to: keys[j].clone()?,
+                waypoints,
+                reversed,
+            })
+        }).collect();
+
+        Layout{
+            vertices,
+            edges,
+            widths: rows.iter().map(|r| r.len()).collect(),
+        }
+    }
+}
+
+/// Stable sort of a row by the mean position of each node's neighbors in the adjacent row.
+/// Nodes without neighbors there keep their current position.
+fn sort_by_barycenter(row: &mut [usize], neighbors: &[Vec<usize>], position: &[usize]) {
+    let barycenter = |n: usize| {
+        if neighbors[n].is_empty() {
+            position[n] as f64
+        }else{
+            neighbors[n].iter().map(|&m| position[m] as f64).sum::<f64>() / neighbors[n].len() as f64
+        }
+    };
+    row.sort_by(|&a, &b| barycenter(a).partial_cmp(&barycenter(b)).unwrap());
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::{DependencyGraph,Property};
+
+    #[test]
+    fn layout() {
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+        graph.insert("A", "Alpha",   vec![]);
+        graph.insert("B", "Bravo",   vec![("A",None)]);
+        graph.insert("C", "Charlie", vec![("B",None),("Z",None)]);
+        graph.insert("D", "Delta",   vec![("A",None),("C",None)]);
+        graph.set_property(&"C", "color", "red");
+
+        let layout = graph.layout();
+        let layer_of = |key| layout.vertices.iter().find(|v| v.key == key).unwrap().layer;
+        assert_eq!(layer_of("A"), 0);
+        assert_eq!(layer_of("Z"), 0);
+        assert_eq!(layer_of("B"), 1);
+        assert_eq!(layer_of("C"), 2);
+        assert_eq!(layer_of("D"), 3);
+        assert!(!layout.vertices.iter().find(|v| v.key == "Z").unwrap().resident);
+        assert_eq!(layout.vertices.iter().find(|v| v.key == "C").unwrap().properties, vec![("color".to_string(), Property::from("red"))]);
+        // Layer 1 also carries a waypoint for C -> Z
+        assert_eq!(layout.widths, vec![2, 3, 2, 1]);
+
+        // D -> A passes through layers 2 and 1 on the way down, alongside C and B
+        let long = layout.edges.iter().find(|e| e.from == "D" && e.to == "A").unwrap();
+        assert_eq!(long.waypoints.iter().map(|w| w.0).collect::<Vec<_>>(), vec![2, 1]);
+        assert!(!long.reversed);
+        assert_eq!(layout.edges.len(), 5);
+    }
+
+    #[test]
+    fn cycle() {
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+        graph.insert("A", "Alpha", vec![("B",None)]);
+        graph.insert("B", "Bravo", vec![("A",None)]);
+
+        let layout = graph.layout();
+        assert_eq!(layout.widths, vec![1, 1]);
+        assert_eq!(layout.edges.iter().filter(|e| e.reversed).count(), 1);
+    }
+}
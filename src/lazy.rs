@@ -0,0 +1,149 @@
+//! Values loaded on demand from a backing store, so that memory use tracks the working set rather than the graph.
+//!
+//! Insert `Lazy` handles in place of values. The graph holds only the handle, and the value is fetched from the
+//! `Backing` when first asked for, by `DependencyGraph::load` or the handle's own `get`, then cached (if caching is
+//! enabled) until evicted. Iteration yields the handles unloaded, so that only the values used are fetched.
+
+use std::borrow::Borrow;
+use std::sync::{Arc,Mutex};
+
+use super::DependencyGraph;
+
+/// A source of values, by key
+pub trait Loader<K,V> {
+    /// The value for `key`, or None if the store has none
+    fn load(&self, key: &K) -> Option<V>;
+}
+
+impl<K,V,F> Loader<K,V> for F where F: Fn(&K) -> Option<V> {
+    fn load(&self, key: &K) -> Option<V> {
+        self(key)
+    }
+}
+
+/// Shared by a backing store and its handles. Send and Sync, so that a graph of handles can be shared across threads
+type SharedLoader<K,V> = Arc<dyn Loader<K,V> + Send + Sync>;
+
+/// A backing store, which hands out `Lazy` values
+pub struct Backing<K,V> {
+    loader: SharedLoader<K,V>,
+    cache: bool,
+}
+
+/// A value held in a `Backing`, loaded when first asked for. Clones share the cached value.
+pub struct Lazy<K,V> {
+    key: K,
+    loader: SharedLoader<K,V>,
+    cache: bool,
+    cached: Arc<Mutex<Option<Arc<V>>>>,
+}
+
+impl<K,V> Backing<K,V> {
+    /// `cache` determines whether loaded values are retained by their `Lazy` handles, or loaded afresh each time
+    pub fn new<L>(loader: L, cache: bool) -> Self where L: Loader<K,V> + Send + Sync + 'static {
+        Backing {
+            loader: Arc::new(loader),
+            cache,
+        }
+    }
+
+    /// A handle to the value for `key`, which is not loaded until asked for
+    pub fn value(&self, key: K) -> Lazy<K,V> {
+        Lazy {
+            key,
+            loader: self.loader.clone(),
+            cache: self.cache,
+            cached: Arc::new(Mutex::new(None)),
+        }
+    }
+}
+
+impl<K,V> Lazy<K,V> {
+    /// The value, loading it if it isn't cached. None if the store has none.
+    pub fn get(&self) -> Option<Arc<V>> {
+        let mut cached = self.cached.lock().unwrap();
+        if let Some(ref value) = *cached {
+            return Some(value.clone());
+        }
+        let value = Arc::new(self.loader.load(&self.key)?);
+        if self.cache {
+            *cached = Some(value.clone());
+        }
+        Some(value)
+    }
+    pub fn is_loaded(&self) -> bool {
+        self.cached.lock().unwrap().is_some()
+    }
+    /// Drop the cached value, if any, to be loaded again when next asked for
+    pub fn evict(&self) {
+        *self.cached.lock().unwrap() = None;
+    }
+}
+
+impl<K,V> Clone for Lazy<K,V> where K: Clone {
+    fn clone(&self) -> Self {
+        Lazy {
+            key: self.key.clone(),
+            loader: self.loader.clone(),
+            cache: self.cache,
+            cached: self.cached.clone(),
+        }
+    }
+}
+
+impl<K,L,W,E> DependencyGraph<K,Lazy<L,W>,E> {
+    /// The value of a resident vertex, loaded from its backing store if it isn't cached. None if the key is not
+    /// resident, or the store has no value for it. The store is consulted without holding the graph lock.
+    pub fn load<Q>(&self, key: &Q) -> Option<Arc<W>> where K: Borrow<Q>, Q: ?Sized+PartialEq, L: Clone {
+        self.get(key)?.get()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize,Ordering};
+    use std::thread;
+    use super::Backing;
+    use super::super::DependencyGraph;
+
+    #[test]
+    fn lazy() {
+        let loads = Arc::new(AtomicUsize::new(0));
+        let counter = loads.clone();
+        let backing = Backing::new(move |k: &&str| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            if *k == "Z" { None } else { Some(k.to_lowercase()) }
+        }, true);
+
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+        graph.insert("A", backing.value("A"), vec![]);
+        graph.insert("B", backing.value("B"), vec![("A",None)]);
+        graph.insert("Z", backing.value("Z"), vec![]);
+        assert_eq!(loads.load(Ordering::SeqCst), 0);
+
+        let values: Vec<String> = graph.iter().filter_map(|i| i.value.get()).map(|v| (*v).clone()).collect();
+        assert_eq!(values, vec!["a","b"]);
+        assert_eq!(loads.load(Ordering::SeqCst), 3);
+
+        // Cached, and shared with the handle in the graph
+        let a = graph.get(&"A").unwrap();
+        assert!(a.is_loaded());
+        assert_eq!(*a.get().unwrap(), "a");
+        assert_eq!(loads.load(Ordering::SeqCst), 3);
+
+        a.evict();
+        assert!(!graph.get(&"A").unwrap().is_loaded());
+        a.get();
+        assert_eq!(loads.load(Ordering::SeqCst), 4);
+
+        // Loaded through the graph, from another thread
+        a.evict();
+        let shared = graph.clone();
+        let loaded = thread::spawn(move || shared.load(&"A").map(|v| (*v).clone())).join().unwrap();
+        assert_eq!(loaded, Some("a".to_string()));
+        assert_eq!(graph.load(&"Z"), None);
+        assert_eq!(graph.load(&"Y"), None);
+        assert_eq!(loads.load(Ordering::SeqCst), 6);
+    }
+}
@@ -10,20 +10,98 @@
 //! TODOs;
 //! * Discontinue use of Arc in favor of custom Arc implementation, to avoid double counting
 //! * Lock-free concurrency
-//! * Iterators reflect midstream graph changes for items topologically ascendent/descendent of present iteration
 
-use std::sync::{Mutex,RwLock,Arc};
+use std::borrow::Borrow;
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+use std::ops::{Deref,DerefMut};
+use std::sync::{Mutex,Arc};
+use std::sync::atomic::{AtomicUsize,Ordering};
+
+use adjacency::{Adjacency,strongly_connected};
+
+mod adjacency;
+mod compare;
+mod connectivity;
+mod convert;
+mod cycles;
+mod dirty;
+mod edges;
+mod entry;
+mod eval;
+mod expiry;
+mod format;
+mod incremental;
+mod layout;
+mod lazy;
+mod limits;
+mod live;
+mod mask;
+mod metrics;
+mod neighbors;
+mod pin;
+mod properties;
+mod provenance;
+mod removal;
+mod schedule;
+mod stream;
+mod structure;
+mod subgraph;
+mod transform;
+mod undirected;
+pub use connectivity::Connectivity;
+pub use convert::BuildError;
+pub use cycles::{CyclePolicy,CycleError};
+pub use entry::{Entry,OccupiedEntry,VacantEntry};
+pub use eval::EvalCache;
+pub use format::Pretty;
+pub use incremental::Engine;
+pub use layout::{Layout,VertexLayout,EdgeLayout};
+pub use lazy::{Backing,Lazy,Loader};
+pub use limits::{Limits,LimitError};
+pub use live::LiveIter;
+pub use metrics::Metrics;
+pub use properties::Property;
+pub use provenance::Provenance;
+pub use removal::{DrainTopo,RemovalPolicy};
+pub use schedule::{Schedule,Registration,ScheduleError};
+pub use stream::{Stream,StreamLoad,LoadProgress};
+pub use structure::Structure;
+pub use subgraph::Direction;
+pub use undirected::Undirected;
 
 struct Vertex<K,V,E> {
     key: Mutex<Option<K>>,
     refcount: Mutex<usize>,
-    state: Mutex<VertexState<K,V,E>>
+    dirty: Mutex<bool>,
+    masked: Mutex<bool>,
+    /// Exempt from automatic removal, such as by `gc`
+    pinned: Mutex<bool>,
+    /// Whether this phantom was previously resident, and removed while still referenced
+    removed: Mutex<bool>,
+    /// Incremented each time the vertex goes from phantom to resident, such that a key removed and inserted again can
+    /// be told apart from its earlier residency, even in the same slot
+    residency: Mutex<usize>,
+    properties: Mutex<Vec<(String,Property)>>,
+    state: Mutex<VertexState<K,V,E>>,
+    /// Those of the `VertexVec` holding this vertex
+    counts: Arc<Counts>,
 }
 
 struct Edge<K,V,E> {
     measure: Option<E>,
+    /// Boxed, as most edges carry none
+    meta: Option<Box<EdgeMeta>>,
     dest_vertex: Arc<Vertex<K,V,E>>
 }
+/// Optional per-edge attributes
+#[derive(Clone,Default)]
+struct EdgeMeta {
+    provenance: Option<Provenance>,
+    /// Timestamp, in the caller's units, at or after which `expire_edges` removes the edge
+    expires: Option<u64>,
+}
+
 enum VertexState<K,V,E>{
     Phantom,
     Resident {
@@ -32,30 +110,166 @@ enum VertexState<K,V,E>{
     }
 }
 
-#[derive(Clone)]
+/// The slots of a graph, with running counts of what they hold
+struct VertexVec<K,V,E> {
+    vertices: Vec<Arc<Vertex<K,V,E>>>,
+    counts: Arc<Counts>,
+}
+/// Maintained as vertices and edges come and go, so that sizes can be read without a scan.
+/// Shared with each vertex, as edges may be dropped where only their destination is to hand.
+#[derive(Default)]
+struct Counts {
+    resident: AtomicUsize,
+    phantoms: AtomicUsize,
+    edges: AtomicUsize,
+    /// Bumped by each change to the graph's structure or masking, for `LiveIter` to tell when its snapshot is stale
+    changes: AtomicUsize,
+}
+/// An `InsertOutcome` listing vertices rather than keys. Each previous edge's vertex is held by the corresponding
+/// edge released alongside it, and so remains keyed for as long as that edge is.
+type Inserted<K,V,E> = InsertOutcome<Arc<Vertex<K,V,E>>,V,E>;
+/// Edges no longer held by their vertex, for the caller to drop once safe to
+type Released<K,V,E> = Vec<Edge<K,V,E>>;
+/// A vertex's edges, as destination keys and measures
+type Edges<K,E> = Vec<(K,Option<E>)>;
+/// The key, value, and edges removed from a vertex by `Vertex::vacate`
+type Vacated<K,V,E> = (K,V,Vec<Edge<K,V,E>>);
+
 pub struct DependencyGraph<K,V,M> {
-    vertex_vec: Arc<Mutex<Vec<Arc<Vertex<K,V,M>>>>>,
+    vertex_vec: Arc<Mutex<VertexVec<K,V,M>>>,
+    /// Enforced on inserts made through this handle
+    limits: Limits,
+    /// Followed by `retain` when called through this handle
+    removal_policy: RemovalPolicy,
 }
 
-impl<K,V,E> Vertex<K,V,E> {
-    fn new(key: K, value: V) -> Self {
-        Vertex {
-            key: Mutex::new(Some(key)),
-            refcount: Mutex::new(0),
-            state: Mutex::new(VertexState::Resident {
-                value: value,
-                edges: Vec::new()
-            })
+/// Cloning yields another handle to the same shared graph, with the same limits and removal policy, so no bounds are
+/// required of K, V, or M
+impl<K,V,M> Clone for DependencyGraph<K,V,M> {
+    fn clone(&self) -> Self {
+        DependencyGraph {
+            vertex_vec: self.vertex_vec.clone(),
+            limits: self.limits,
+            removal_policy: self.removal_policy,
+        }
+    }
+}
+
+impl<K,V,E> VertexVec<K,V,E> {
+    fn with_capacity(capacity: usize) -> Self {
+        VertexVec{
+            vertices: Vec::with_capacity(capacity),
+            counts: Arc::default(),
+        }
+    }
+
+    /// Insert or update the vertex for `key`, as `DependencyGraph::insert`, without regard for limits.
+    /// Also returns the previous edges, stripped of their measures, for the caller to drop once it has read the outcome.
+    fn insert(&mut self, key: K, value: V, mut edge_tuples: Vec<(K,Option<E>)>, meta: Option<&EdgeMeta>)
+        -> (Inserted<K,V,E>,Released<K,V,E>)
+        where K: PartialEq {
+        let existing = Vertex::find(&key, self).cloned();
+
+        let mut created_phantoms = Vec::new();
+        let edges = edge_tuples.drain(..).map(|(k,m)| {
+            let created = k != key && Vertex::find(&k, self).is_none();
+            let edge = Edge::new(k, m, meta, self);
+            if created {
+                created_phantoms.push(edge.dest_vertex.clone());
+            }
+            edge
+        }).collect();
+
+        let (vertex, kind) = match existing {
+            // Already counted for its residency
+            Some(ref vertex) if vertex.is_resident() => (vertex.clone(), InsertKind::Updated),
+            Some(_) => (Vertex::assert( key, self, VertexState::Phantom ), InsertKind::Promoted),
+            None => (Vertex::assert( key, self, VertexState::Phantom ), InsertKind::Created),
+        };
+
+        // The previous edges are released only after the state lock is dropped, as they may point back at this vertex
+        let (previous, released) = match vertex.make_resident(value, edges) {
+            VertexState::Resident{ value, mut edges } => {
+                let previous_edges = edges.iter_mut().map(|e| (e.dest_vertex.clone(), e.measure.take())).collect();
+                (Some((value, previous_edges)), edges)
+            },
+            VertexState::Phantom => (None, Vec::new()),
+        };
+
+        (InsertOutcome{
+            created_phantoms,
+            previous,
+            kind,
+        }, released)
+    }
+}
+impl<K,V,E> VertexVec<K,V,E> {
+    /// Empty every slot, returning the key and value of each resident vertex. The slots are dropped, but their capacity retained.
+    fn drain(&mut self) -> Vec<(K,V)> {
+        let mut drained = Vec::new();
+        let mut edges = Vec::new();
+        for vertex in self.vertices.iter() {
+            let key = vertex.key.lock().unwrap().take();
+            let state = std::mem::replace(&mut *vertex.state.lock().unwrap(), VertexState::Phantom);
+            if let (Some(key), VertexState::Resident{ value, edges: e }) = (key, state) {
+                drained.push((key, value));
+                edges.push(e);
+            }
+        }
+        // Dropped only now that no state lock is held. Vertices would otherwise keep one another alive through their edges
+        drop(edges);
+        self.vertices.clear();
+        // The vertices weren't vacated one by one, so the counts start afresh
+        self.counts = Arc::default();
+        drained
+    }
+}
+impl<K,V,E> Default for VertexVec<K,V,E> {
+    fn default() -> Self {
+        VertexVec::with_capacity(0)
+    }
+}
+impl<K,V,E> Deref for VertexVec<K,V,E> {
+    type Target = Vec<Arc<Vertex<K,V,E>>>;
+    fn deref(&self) -> &Self::Target {
+        &self.vertices
+    }
+}
+impl<K,V,E> DerefMut for VertexVec<K,V,E> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.vertices
+    }
+}
+
+impl Counts {
+    fn add(count: &AtomicUsize, n: isize) {
+        if n < 0 {
+            count.fetch_sub(n.unsigned_abs(), Ordering::Relaxed);
+        }else{
+            count.fetch_add(n as usize, Ordering::Relaxed);
         }
     }
+    fn get(count: &AtomicUsize) -> usize {
+        count.load(Ordering::Relaxed)
+    }
+    fn changed(&self) {
+        Counts::add(&self.changes, 1);
+    }
+}
 
+impl<K,V,E> Vertex<K,V,E> {
+    /// Find the occupied vertex for a given key, if any
+    fn find<'a,Q> (key: &Q, vertex_vec: &'a [Arc<Vertex<K,V,E>>]) -> Option<&'a Arc<Self>>
+        where K: Borrow<Q>, Q: ?Sized+PartialEq {
+        vertex_vec.iter().find(|n| n.key.lock().unwrap().as_ref().map(Borrow::borrow) == Some(key) )
+    }
     /// Find the dest vertex, or create using a given VertexState
     /// Either way increment its refcount
     /// For the time being, this refcount is redundant with that of the Arc. This will be remedied later.
-    fn assert (key: K, vertex_vec: &mut Vec<Arc<Vertex<K,V,E>>>, default_state: VertexState<K,V,E>) -> Arc<Self>
-        where K: PartialEq+Ord {
+    fn assert (key: K, vertex_vec: &mut VertexVec<K,V,E>, default_state: VertexState<K,V,E>) -> Arc<Self>
+        where K: PartialEq {
         //match vertex_vec.binary_search_by(|n| n.key.lock().unwrap().cmp(&Some(key)) ) {
-        match vertex_vec.iter().find(|n| *n.key.lock().unwrap() == Some(key) ) {
+        match vertex_vec.iter().find(|n| n.key.lock().unwrap().as_ref() == Some(&key) ) {
                 //Ok(i) => {
                 Some(vertex) => {
                     //let vertex = vertex_vec[i].clone();
@@ -64,12 +278,22 @@ impl<K,V,E> Vertex<K,V,E> {
                 }
                 //Err(i) => {
                 None => {
+                    match default_state {
+                        VertexState::Phantom => Counts::add(&vertex_vec.counts.phantoms, 1),
+                        VertexState::Resident{ .. } => Counts::add(&vertex_vec.counts.resident, 1),
+                    }
+                    vertex_vec.counts.changed();
                     // Seaerch for an empty slot
-                    match vertex_vec.iter().find(|n| *n.key.lock().unwrap() == None) {
+                    match vertex_vec.iter().find(|n| n.key.lock().unwrap().is_none()) {
                         Some(vertex) => {
                             // Found one
                             *vertex.key.lock().unwrap() = Some(key);
                             *vertex.refcount.lock().unwrap() = 1;
+                            *vertex.dirty.lock().unwrap() = false;
+                            *vertex.masked.lock().unwrap() = false;
+                            *vertex.pinned.lock().unwrap() = false;
+                            *vertex.removed.lock().unwrap() = false;
+                            vertex.properties.lock().unwrap().clear();
                             *vertex.state.lock().unwrap() = default_state;
                             vertex.clone()
                         },
@@ -78,8 +302,15 @@ impl<K,V,E> Vertex<K,V,E> {
                             let vertex = Arc::new(Vertex{
                                 key: Mutex::new(Some(key)),
                                 refcount: Mutex::new(1),
-                                state: Mutex::new(default_state)
-                            });;
+                                dirty: Mutex::new(false),
+                                masked: Mutex::new(false),
+                                pinned: Mutex::new(false),
+                                removed: Mutex::new(false),
+                                residency: Mutex::new(0),
+                                properties: Mutex::new(Vec::new()),
+                                state: Mutex::new(default_state),
+                                counts: vertex_vec.counts.clone(),
+                            });
                             vertex_vec.push(vertex.clone());
                             //vertex_vec.insert(i, vertex.clone());
 
@@ -89,15 +320,79 @@ impl<K,V,E> Vertex<K,V,E> {
                 }
             }
     }
+    /// Make this vertex resident with `value` and `edges`, returning its previous state
+    fn make_resident(&self, value: V, edges: Vec<Edge<K,V,E>>) -> VertexState<K,V,E> {
+        let previous = std::mem::replace(&mut *self.state.lock().unwrap(), VertexState::Resident{ value, edges });
+        *self.removed.lock().unwrap() = false;
+        self.counts.changed();
+        if let VertexState::Phantom = previous {
+            Counts::add(&self.counts.phantoms, -1);
+            Counts::add(&self.counts.resident, 1);
+            *self.residency.lock().unwrap() += 1;
+        }
+        previous
+    }
+    fn is_resident(&self) -> bool {
+        match *self.state.lock().unwrap() {
+            VertexState::Resident{ .. } => true,
+            VertexState::Phantom => false,
+        }
+    }
+    /// Remove the value and edges of a resident vertex, returning its key, value, and edges. It remains as a phantom while
+    /// still referenced, and otherwise vacates its slot. The caller must hold the `vertex_vec` lock, and should drop
+    /// the edges once it's done with them, as the vertex's dependencies remain referenced until then.
+    fn vacate(&self) -> Option<Vacated<K,V,E>> where K: Clone {
+        let previous = {
+            let mut state = self.state.lock().unwrap();
+            if let VertexState::Phantom = *state {
+                return None;
+            }
+            std::mem::replace(&mut *state, VertexState::Phantom)
+        };
+        let (value, edges) = match previous {
+            VertexState::Resident{ value, edges } => (value, edges),
+            VertexState::Phantom => unreachable!(),
+        };
+
+        self.properties.lock().unwrap().clear();
+        *self.dirty.lock().unwrap() = false;
+        *self.masked.lock().unwrap() = false;
+        *self.pinned.lock().unwrap() = false;
+        *self.removed.lock().unwrap() = true;
+
+        // Release the count held for residency. The edges, which may point back at this vertex, still hold theirs
+        let mut refcount = self.refcount.lock().unwrap();
+        let mut key = self.key.lock().unwrap();
+        *refcount -= 1;
+        Counts::add(&self.counts.resident, -1);
+        self.counts.changed();
+        let key = if *refcount == 0 {
+            key.take()
+        }else{
+            Counts::add(&self.counts.phantoms, 1);
+            key.clone()
+        };
+        key.map(|key| (key, value, edges))
+    }
+    fn status(&self) -> VertexStatus {
+        match (self.is_resident(), *self.masked.lock().unwrap(), *self.removed.lock().unwrap()) {
+            (true, false, _) => VertexStatus::Resident,
+            (true, true, _) => VertexStatus::Masked,
+            (false, _, false) => VertexStatus::Phantom,
+            (false, _, true) => VertexStatus::Removed,
+        }
+    }
     fn increment(&self) {
         *self.refcount.lock().unwrap() += 1;
     }
     fn decrement (&self) {
-        let refcount = self.refcount.lock().unwrap();
+        let mut refcount = self.refcount.lock().unwrap();
         *refcount -= 1;
         if *refcount == 0 {
             if let VertexState::Phantom = *self.state.lock().unwrap() {
                 *self.key.lock().unwrap() = None;
+                Counts::add(&self.counts.phantoms, -1);
+                self.counts.changed();
             }
         }
     }
@@ -109,10 +404,13 @@ impl<K,V,E> VertexState<K,V,E> {
 }
 
 impl <K,V,E> Edge<K,V,E>{
-    fn new (dest_key: K, measure: Option<E>, vertex_vec: &mut Vec<Arc<Vertex<K,V,E>>>) -> Self 
-        where K: Ord {
+    fn new (dest_key: K, measure: Option<E>, meta: Option<&EdgeMeta>, vertex_vec: &mut VertexVec<K,V,E>) -> Self 
+        where K: PartialEq {
+            Counts::add(&vertex_vec.counts.edges, 1);
+            vertex_vec.counts.changed();
             Edge{ 
                 measure,
+                meta: meta.map(|m| Box::new(m.clone())),
                 dest_vertex: Vertex::assert( dest_key, vertex_vec, VertexState::Phantom )
             }
     }
@@ -120,335 +418,698 @@ impl <K,V,E> Edge<K,V,E>{
 impl <K,V,E> Drop for Edge<K,V,E> {
     fn drop (&mut self) {
         // Droping this edge, decrement the dest_vertex refcount
+        Counts::add(&self.dest_vertex.counts.edges, -1);
+        self.dest_vertex.counts.changed();
         self.dest_vertex.decrement()
     }
 }
 
+/// Where a vertex is in its lifecycle, as returned by `DependencyGraph::status`
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum VertexStatus {
+    /// Neither inserted nor referenced, such that the graph has no vertex for the key at all. Distinct from `Phantom`,
+    /// as nothing is waiting on it
+    Missing,
+    /// Referenced by other vertices, but not yet inserted
+    Phantom,
+    Resident,
+    /// Resident, but passed over by traversals until unmasked
+    Masked,
+    /// Previously resident, and since removed, but still referenced by other vertices
+    Removed,
+}
+
+/// Why a `resolve` was refused, leaving the graph unchanged
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum ResolveError {
+    /// The key was already resident, as it would be after a duplicate delivery
+    AlreadyResident,
+    Limit(LimitError),
+}
+
+/// What an `insert` did
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum InsertKind {
+    /// The key was not previously present
+    Created,
+    /// The key was already resident, and its value and edges were replaced
+    Updated,
+    /// The key was a phantom, referenced by other vertices but not yet inserted
+    Promoted,
+}
+
+/// Returned by `insert`
+#[derive(Clone,Debug,PartialEq)]
+pub struct InsertOutcome<K,V,E> {
+    /// Dependencies which were not previously present, and are now phantoms
+    pub created_phantoms: Vec<K>,
+    /// The value and edges replaced, if the key was already resident
+    pub previous: Option<(V,Edges<K,E>)>,
+    pub kind: InsertKind,
+}
+
+impl<K,V,E> InsertOutcome<Arc<Vertex<K,V,E>>,V,E> {
+    /// Must be called while the vertex_vec lock is held, and before the released edges are dropped
+    fn keyed(self) -> InsertOutcome<K,V,E> where K: Clone {
+        let key = |v: &Arc<Vertex<K,V,E>>| v.key.lock().unwrap().clone();
+        InsertOutcome{
+            created_phantoms: self.created_phantoms.iter().filter_map(key).collect(),
+            previous: self.previous.map(|(value, edges)| {
+                (value, edges.into_iter().filter_map(|(v, m)| Some((key(&v)?, m))).collect())
+            }),
+            kind: self.kind,
+        }
+    }
+}
+
+impl<K,V,E> Default for DependencyGraph<K,V,E> {
+    fn default() -> Self {
+        DependencyGraph::new()
+    }
+}
+
 impl<K,V,E> DependencyGraph<K,V,E> {
     pub fn new() -> DependencyGraph<K,V,E> {
+        DependencyGraph::with_capacity(30)
+    }
+    /// An empty graph with room for `capacity` vertices, resident or phantom, before reallocating
+    pub fn with_capacity(capacity: usize) -> DependencyGraph<K,V,E> {
         DependencyGraph {
-            vertex_vec: Arc::new(Mutex::new(Vec::with_capacity(30))),
+            vertex_vec: Arc::new(Mutex::new(VertexVec::with_capacity(capacity))),
+            limits: Limits::default(),
+            removal_policy: RemovalPolicy::default(),
+        }
+    }
+    /// An independent copy of the graph, unlike `clone`, which shares it. Values, measures, edge attributes,
+    /// properties, and flags such as masking and pinning are all copied, as are this handle's limits and removal policy.
+    pub fn deep_clone(&self) -> Self where K: PartialEq+Clone, V: Clone, E: Clone {
+        let vertex_vec = self.vertex_vec.lock().unwrap();
+        let mut copy = VertexVec::with_capacity(vertex_vec.len());
+        for vertex in vertex_vec.iter() {
+            let key = match *vertex.key.lock().unwrap() {
+                Some(ref key) => key.clone(),
+                None => continue,
+            };
+            let (value, edges) = match *vertex.state.lock().unwrap() {
+                VertexState::Resident{ ref value, ref edges } => {
+                    let edges: Vec<Edge<K,V,E>> = edges.iter().filter_map(|e| {
+                        let k = e.dest_vertex.key.lock().unwrap().clone()?;
+                        Some(Edge::new(k, e.measure.clone(), e.meta.as_deref(), &mut copy))
+                    }).collect();
+                    (value.clone(), edges)
+                },
+                VertexState::Phantom => continue,
+            };
+            let copied = Vertex::assert(key, &mut copy, VertexState::Phantom);
+            copied.make_resident(value, edges);
+            *copied.dirty.lock().unwrap() = *vertex.dirty.lock().unwrap();
+            *copied.masked.lock().unwrap() = *vertex.masked.lock().unwrap();
+            *copied.pinned.lock().unwrap() = *vertex.pinned.lock().unwrap();
+            *copied.properties.lock().unwrap() = vertex.properties.lock().unwrap().clone();
+        }
+        // Phantoms exist in the copy by now, as the edges to them do
+        for vertex in vertex_vec.iter().filter(|vertex| *vertex.removed.lock().unwrap()) {
+            if let Some(ref key) = *vertex.key.lock().unwrap() {
+                if let Some(copied) = Vertex::find(key, &copy) {
+                    *copied.removed.lock().unwrap() = true;
+                }
+            }
+        }
+        DependencyGraph {
+            vertex_vec: Arc::new(Mutex::new(copy)),
+            limits: self.limits,
+            removal_policy: self.removal_policy,
         }
     }
 
     /// Insert a value and Vec of dependencies for a given key. If the Graph already had this key, the value is updated.
     /// Dependencies which are not already inserted will be created as phantom Vertexs.
-    pub fn insert(&mut self, key: K, value: V, edge_tuples: Vec<(K,Option<E>)>)
-        where K: PartialEq+Ord, V: Clone {
+    ///
+    /// Panics if the insert would exceed this handle's `Limits`. Use `try_insert` where those are configured.
+    pub fn insert(&mut self, key: K, value: V, edge_tuples: Vec<(K,Option<E>)>) -> InsertOutcome<K,V,E>
+        where K: PartialEq+Clone {
+        self.insert_attributed(key, value, edge_tuples, None, InsertOutcome::keyed)
+    }
+    /// As `insert`, but where the key is already resident, `merge` the new value into the existing one rather than replacing
+    /// it. The edges are replaced either way. The outcome's `previous` is thus always None.
+    ///
+    /// Panics if the insert would exceed this handle's `Limits`.
+    pub fn upsert<F>(&mut self, key: K, value: V, edge_tuples: Vec<(K,Option<E>)>, merge: F) -> InsertOutcome<K,V,E>
+        where K: PartialEq+Clone, F: FnOnce(&mut V, V) {
+        let mut vertex_vec = self.vertex_vec.lock().unwrap();
+        if let Err(error) = self.limits.check(&vertex_vec, &key, &edge_tuples) {
+            // Released first, so as not to poison it
+            drop(vertex_vec);
+            limits::exceeded(error);
+        }
+        let existing = Vertex::find(&key, &vertex_vec).cloned();
+        let (mut outcome, released) = vertex_vec.insert(key, value, edge_tuples, None);
+        if let (Some((mut merged, _)), Some(vertex)) = (outcome.previous.take(), existing) {
+            if let VertexState::Resident{ ref mut value, .. } = *vertex.state.lock().unwrap() {
+                // Put the existing value back, and merge the new one into it
+                std::mem::swap(value, &mut merged);
+                merge(value, merged);
+            }
+        }
+        let outcome = outcome.keyed();
+        drop(released);
+        outcome
+    }
+    /// Insert each `(key, value, edges)` tuple in turn, as `insert`, under a single acquisition of the graph lock.
+    /// Returns the outcome of each. Use `extend` where the outcomes aren't needed.
+    ///
+    /// Panics if an insert would exceed this handle's `Limits`, leaving those before it inserted.
+    pub fn insert_batch(&mut self, batch: Vec<(K,V,Edges<K,E>)>) -> Vec<InsertOutcome<K,V,E>> where K: PartialEq+Clone {
+        let mut vertex_vec = self.vertex_vec.lock().unwrap();
+        let mut outcomes = Vec::with_capacity(batch.len());
+        for (key, value, edge_tuples) in batch {
+            if let Err(error) = self.limits.check(&vertex_vec, &key, &edge_tuples) {
+                // Released first, so as not to poison it
+                drop(vertex_vec);
+                limits::exceeded(error);
+            }
+            let (outcome, released) = vertex_vec.insert(key, value, edge_tuples, None);
+            outcomes.push(outcome.keyed());
+            drop(released);
+        }
+        outcomes
+    }
+    /// As `insert`, but failing, with the graph unchanged, if the insert would exceed this handle's `Limits`
+    pub fn try_insert(&mut self, key: K, value: V, edge_tuples: Vec<(K,Option<E>)>) -> Result<InsertOutcome<K,V,E>,LimitError>
+        where K: PartialEq+Clone {
+        self.try_insert_attributed(key, value, edge_tuples, None, InsertOutcome::keyed)
+    }
+    /// As `insert`, but only of a key which is not already resident, typically to fill in a phantom. Rather than being
+    /// replaced, a resident vertex is left as it is, and `ResolveError::AlreadyResident` returned.
+    pub fn resolve(&mut self, key: K, value: V, edge_tuples: Vec<(K,Option<E>)>) -> Result<InsertOutcome<K,V,E>,ResolveError>
+        where K: PartialEq+Clone {
+        let mut vertex_vec = self.vertex_vec.lock().unwrap();
+        if Vertex::find(&key, &vertex_vec).is_some_and(|vertex| vertex.is_resident()) {
+            return Err(ResolveError::AlreadyResident);
+        }
+        self.limits.check(&vertex_vec, &key, &edge_tuples).map_err(ResolveError::Limit)?;
+        // Nothing to release, as the vertex had no edges
+        let (outcome, _) = vertex_vec.insert(key, value, edge_tuples, None);
+        Ok(outcome.keyed())
+    }
+    /// Returns the result of `finish`, called with the outcome while the graph is still locked. Callers which discard the
+    /// outcome can pass `drop`, so as not to require `K: Clone`.
+    fn insert_attributed<R,F>(&mut self, key: K, value: V, edge_tuples: Vec<(K,Option<E>)>, meta: Option<&EdgeMeta>, finish: F) -> R
+        where K: PartialEq, F: FnOnce(Inserted<K,V,E>) -> R {
+        match self.try_insert_attributed(key, value, edge_tuples, meta, finish) {
+            Ok(outcome) => outcome,
+            Err(error) => limits::exceeded(error),
+        }
+    }
+    fn try_insert_attributed<R,F>(&mut self, key: K, value: V, edge_tuples: Vec<(K,Option<E>)>, meta: Option<&EdgeMeta>, finish: F)
+        -> Result<R,LimitError>
+        where K: PartialEq, F: FnOnce(Inserted<K,V,E>) -> R {
+        let mut vertex_vec = self.vertex_vec.lock().unwrap();
+        self.limits.check(&vertex_vec, &key, &edge_tuples)?;
+        let (outcome, released) = vertex_vec.insert(key, value, edge_tuples, meta);
+        let result = finish(outcome);
+        // Only now that the outcome has been read, as releasing an edge may vacate its destination
+        drop(released);
+        Ok(result)
+    }
+    /// Remove a resident vertex and its edges. It remains as a phantom while other vertices still depend on it.
+    pub fn remove(&mut self, key: K ) where K: PartialEq+Clone {
+        self.take(&key);
+    }
+    /// As `remove`, returning the removed value rather than dropping it. None if the key was not resident.
+    pub fn take<Q>(&mut self, key: &Q) -> Option<V> where K: Borrow<Q>+Clone, Q: ?Sized+PartialEq {
         let vertex_vec = self.vertex_vec.lock().unwrap();
+        let (_, value, edges) = Vertex::find(key, &vertex_vec)?.vacate()?;
+        // Releasing the dependencies, now that no state lock is held
+        drop(edges);
+        Some(value)
+    }
 
-        let edges = edge_tuples.drain(..).map(|(k,m)| Edge::new(k, m, &mut *vertex_vec) ).collect();
-        let vertex = Vertex::assert( key, &mut *vertex_vec, VertexState::Phantom );
-        *vertex.state.lock().unwrap() = VertexState::Resident{
-            value,
-            edges: edges
-        };
+    /// Atomically replace the contents of this graph, as seen by every handle to it, with those of `contents`.
+    /// Returns a new graph holding the previous contents. Any other handles to `contents` are left empty.
+    pub fn replace_contents(&mut self, contents: DependencyGraph<K,V,E>) -> DependencyGraph<K,V,E> {
+        if Arc::ptr_eq(&self.vertex_vec, &contents.vertex_vec) {
+            return DependencyGraph::new();
+        }
+        // Take the new contents before locking this graph, so that two graphs can never be locked at once
+        let replacement = std::mem::take(&mut *contents.vertex_vec.lock().unwrap());
+        let previous = std::mem::replace(&mut *self.vertex_vec.lock().unwrap(), replacement);
+        DependencyGraph {
+            vertex_vec: Arc::new(Mutex::new(previous)),
+            limits: self.limits,
+            removal_policy: self.removal_policy,
+        }
+    }
+
+    /// Remove every vertex and edge, as seen by every handle to this graph, and release the capacity held for them
+    pub fn clear(&mut self) {
+        let mut vertex_vec = self.vertex_vec.lock().unwrap();
+        vertex_vec.drain();
+        vertex_vec.shrink_to_fit();
+    }
+    /// The number of vertices, resident or phantom, the graph can hold before reallocating. Slots vacated by removals
+    /// are reused, and so count toward this until reclaimed by `shrink_to_fit`.
+    pub fn capacity(&self) -> usize {
+        self.vertex_vec.lock().unwrap().capacity()
+    }
+    /// Make room for at least `additional` more vertices, resident or phantom, beyond those present
+    pub fn reserve(&mut self, additional: usize) {
+        let mut vertex_vec = self.vertex_vec.lock().unwrap();
+        let vacant = vertex_vec.iter().filter(|vertex| vertex.key.lock().unwrap().is_none()).count();
+        vertex_vec.reserve(additional.saturating_sub(vacant));
+    }
+    /// Reclaim the slots of removed vertices, and release any capacity beyond those remaining
+    pub fn shrink_to_fit(&mut self) {
+        let mut vertex_vec = self.vertex_vec.lock().unwrap();
+        vertex_vec.retain(|vertex| vertex.key.lock().unwrap().is_some());
+        vertex_vec.shrink_to_fit();
+    }
+    /// As `clear`, yielding the key and value of each resident vertex, in no particular order, and retaining the
+    /// capacity for reuse. The graph is emptied at once, rather than as the iterator is consumed.
+    pub fn drain(&mut self) -> std::vec::IntoIter<(K,V)> {
+        self.vertex_vec.lock().unwrap().drain().into_iter()
+    }
+
+    /// Iterate over the resident vertices in topological order, dependencies first.
+    /// Phantoms and masked vertices are skipped, and cycles are broken arbitrarily, unless otherwise chosen by `iter_with`.
+    ///
+    /// Of the vertices ready at once, the one with the most incoming edges goes first, and then the one in the later
+    /// slot. The order is thus repeatable for a given history of inserts and removals, but isn't decided by the keys,
+    /// as those needn't be `Ord`. Use `iter_by_key` for that.
+    pub fn iter(&self) -> TopoIter<K,V,E> where K: Clone, V: Clone {
+        TopoIter::new(&self.vertex_vec.lock().unwrap(), |value, _| value.clone())
+    }
+    /// As `iter`, in the opposite order, such that each vertex precedes everything it depends on, eg: for teardown.
+    /// The iterator treats dependents as it otherwise would dependencies, so `TraversalContext` and `prune_current`
+    /// refer to those.
+    pub fn iter_rev(&self) -> TopoIter<K,V,E> where K: Clone, V: Clone {
+        let vertex_vec = self.vertex_vec.lock().unwrap();
+        let transposed = Adjacency::new(&vertex_vec).transposed();
+        TopoIter::restricted(&vertex_vec, &transposed, &vec![true; vertex_vec.len()], |value, _| value.clone())
+    }
+    /// As `iter`, but reproducible: of the vertices whose dependencies have all been yielded, the least key goes next.
+    /// Cycles are broken as by `iter`.
+    pub fn iter_by_key(&self) -> TopoIter<K,V,E> where K: Ord+Clone, V: Clone {
+        let vertex_vec = self.vertex_vec.lock().unwrap();
+        let adjacency = Adjacency::new(&vertex_vec);
+        let keys: Vec<Option<K>> = vertex_vec.iter().map(|vertex| vertex.key.lock().unwrap().clone()).collect();
+        let mut by_key: Vec<usize> = (0..keys.len()).collect();
+        by_key.sort_by(|&a, &b| keys[a].cmp(&keys[b]));
+        let mut rank = vec![0; keys.len()];
+        for (r, i) in by_key.into_iter().enumerate() {
+            rank[i] = r;
+        }
+        let order = adjacency.topo_order_ranked(&rank);
+        TopoIter::ordered(&vertex_vec, &adjacency, order, &vec![true; vertex_vec.len()], |value, _| value.clone())
+    }
+    /// As `iter`, with each item's value being `project` of the vertex's, rather than a clone of it. This allows
+    /// iterating over values which aren't `Clone`, as whatever part of them is needed, or over `Arc`s they're held in.
+    /// The graph is locked while `project` is called, so it must not call back into it.
+    pub fn iter_map<W,F>(&self, mut project: F) -> TopoIter<K,W,E> where K: Clone, F: FnMut(&V) -> W {
+        TopoIter::new(&self.vertex_vec.lock().unwrap(), |value, _| project(value))
+    }
+    /// As `iter`, with each item's value paired with its edges, as destination keys and measures in edge order
+    pub fn iter_with_edges(&self) -> TopoIter<K,(V,Edges<K,E>),E> where K: Clone, V: Clone, E: Clone {
+        TopoIter::new(&self.vertex_vec.lock().unwrap(), |value, edges| {
+            let edges = edges.iter()
+                .filter_map(|e| Some((e.dest_vertex.key.lock().unwrap().clone()?, e.measure.clone())))
+                .collect();
+            (value.clone(), edges)
+        })
+    }
+    /// As `iter`, of only `roots` and the vertices they transitively depend on, eg: a build target and its prerequisites.
+    /// Phantom and masked vertices are not followed, so their own dependencies are only included if otherwise required.
+    pub fn iter_from(&self, roots: &[K]) -> TopoIter<K,V,E> where K: PartialEq+Clone, V: Clone {
+        let vertex_vec = self.vertex_vec.lock().unwrap();
+        let adjacency = Adjacency::new(&vertex_vec);
+        let roots: Vec<usize> = (0..vertex_vec.len())
+            .filter(|&i| vertex_vec[i].key.lock().unwrap().as_ref().is_some_and(|key| roots.contains(key)))
+            .collect();
+        let included = adjacency.reachable(&roots, &adjacency.out);
+        TopoIter::restricted(&vertex_vec, &adjacency, &included, |value, _| value.clone())
+    }
+    /// As `iter`, of only the vertices which transitively depend on `key`, eg: everything to re-run once it changes.
+    /// `key` itself is not included, and masked vertices are not followed.
+    pub fn iter_dependents_of(&self, key: &K) -> TopoIter<K,V,E> where K: PartialEq+Clone, V: Clone {
+        let vertex_vec = self.vertex_vec.lock().unwrap();
+        let adjacency = Adjacency::new(&vertex_vec);
+        let mut included = vec![false; vertex_vec.len()];
+        if let Some(i) = vertex_vec.iter().position(|v| v.key.lock().unwrap().as_ref() == Some(key)) {
+            let dependents: Vec<usize> = adjacency.inc[i].iter().cloned().filter(|&j| j != i).collect();
+            included = adjacency.reachable(&dependents, &adjacency.inc);
+            included[i] = false;
+        }
+        TopoIter::restricted(&vertex_vec, &adjacency, &included, |value, _| value.clone())
+    }
+    /// The resident keys among `keys`, ordered as `iter` would order them, so that paths through vertices not among
+    /// `keys` are still respected. Absent, phantom, and masked keys are omitted.
+    pub fn topo_order_of(&self, keys: &[K]) -> Vec<K> where K: PartialEq+Clone {
+        let vertex_vec = self.vertex_vec.lock().unwrap();
+        let adjacency = Adjacency::new(&vertex_vec);
+        adjacency.topo_order().into_iter().filter_map(|i| {
+            match *vertex_vec[i].key.lock().unwrap() {
+                Some(ref key) if keys.contains(key) => Some(key.clone()),
+                _ => None,
+            }
+        }).collect()
+    }
+
+    /// The number of resident vertices, including masked ones
+    pub fn len(&self) -> usize {
+        Counts::get(&self.vertex_vec.lock().unwrap().counts.resident)
+    }
+    /// Returns true if the `DependencyGraph` has no resident vertices
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// The number of vertices referenced, but not resident
+    pub fn phantom_count(&self) -> usize {
+        Counts::get(&self.vertex_vec.lock().unwrap().counts.phantoms)
+    }
+    /// The number of edges from resident vertices
+    pub fn edge_count(&self) -> usize {
+        Counts::get(&self.vertex_vec.lock().unwrap().counts.edges)
+    }
+
+    /// The keys of every resident vertex, in no particular order, as of now
+    pub fn keys(&self) -> std::vec::IntoIter<K> where K: Clone {
+        let vertex_vec = self.vertex_vec.lock().unwrap();
+        let keys: Vec<K> = vertex_vec.iter()
+            .filter(|vertex| vertex.is_resident())
+            .filter_map(|vertex| vertex.key.lock().unwrap().clone())
+            .collect();
+        keys.into_iter()
+    }
+    /// Clones of the values of every resident vertex, in the same order as `keys`, as of now
+    pub fn values(&self) -> std::vec::IntoIter<V> where V: Clone {
+        let vertex_vec = self.vertex_vec.lock().unwrap();
+        let values: Vec<V> = vertex_vec.iter().filter_map(|vertex| {
+            match (vertex.key.lock().unwrap().is_some(), &*vertex.state.lock().unwrap()) {
+                (true, VertexState::Resident{ value, .. }) => Some(value.clone()),
+                _ => None,
+            }
+        }).collect();
+        values.into_iter()
+    }
+    /// The keys referenced by edges but never yet inserted, in no particular order, as of now. Keys which were inserted
+    /// and since removed, while still referenced, are not included.
+    pub fn phantom_keys(&self) -> std::vec::IntoIter<K> where K: Clone {
+        let vertex_vec = self.vertex_vec.lock().unwrap();
+        let keys: Vec<K> = vertex_vec.iter()
+            .filter(|vertex| vertex.status() == VertexStatus::Phantom)
+            .filter_map(|vertex| vertex.key.lock().unwrap().clone())
+            .collect();
+        keys.into_iter()
+    }
+    /// The status of the vertex for a key, `Missing` if the key is neither resident nor referenced
+    pub fn status<Q>(&self, key: &Q) -> VertexStatus where K: Borrow<Q>, Q: ?Sized+PartialEq {
+        let vertex_vec = self.vertex_vec.lock().unwrap();
+        Vertex::find(key, &vertex_vec).map_or(VertexStatus::Missing, |vertex| vertex.status())
+    }
+
+    /// Whether the graph has a vertex for a key, resident or phantom
+    pub fn contains_key<Q>(&self, key: &Q) -> bool where K: Borrow<Q>, Q: ?Sized+PartialEq {
+        let vertex_vec = self.vertex_vec.lock().unwrap();
+        Vertex::find(key, &vertex_vec).is_some()
+    }
+    /// Whether a key has been inserted, and not since removed. Masked vertices are still resident
+    pub fn contains_resident<Q>(&self, key: &Q) -> bool where K: Borrow<Q>, Q: ?Sized+PartialEq {
+        let vertex_vec = self.vertex_vec.lock().unwrap();
+        Vertex::find(key, &vertex_vec).is_some_and(|vertex| vertex.is_resident())
+    }
+
+    /// A clone of the value for a key, if resident
+    pub fn get<Q>(&self, key: &Q) -> Option<V> where K: Borrow<Q>, Q: ?Sized+PartialEq, V: Clone {
+        self.get_with(key, V::clone)
     }
-    pub fn remove(&mut self, key: K ) where K: PartialEq, V: Clone  {
-        unimplemented!()
-    }
-
-    pub fn iter(&self) -> TopoIter<K,V,E> {
-        TopoIter::new(self.vertex_vec.clone())
-    }
-    // /// Returns true if the `DependencyGraph` contains no entries.
-    // #[allow(dead_code)]
-    // pub fn is_empty(&self) -> bool {
-    //     unimplemented!()
-    // }
-
-    // pub fn keys(&self) -> Vec<K> {
-    //     self.Vertexs
-    //         .iter()
-    //         .filter_map(|i| {
-    //             if let &Some(ref Vertex) = i {
-    //                 Some(Vertex.key)
-    //             } else {
-    //                 None
-    //             }
-    //         })
-    //         .collect()
-    // }
-    // pub fn get(&mut self, key: K) -> Option<V> where K: PartialEq, P: Clone {
-    //     if let Some(&Some(ref mut Vertex)) =
-    //          self.Vertexs.iter().find(|i| {
-    //             if let &&Some(ref it) = i {
-    //                 it.key == key
-    //             } else {
-    //                 false
-    //             }
-    //         }) {
-    //         Vertex.clone()
-    //     } else {
-    //         None
-    //     }
-    // }
-    // pub fn remove(&mut self, key: K ) where K: PartialEq, P: Clone  {
-    //     if let Some(Vertex_id) = self.Vertexs.iter().position(|i| {
-    //         if let &Some(ref it) = i {
-    //             it.key == key
-    //         } else {
-    //             false
-    //         }
-    //     }) {
-    //         let mut full_remove = false;
-    //         let mut relations = Vec::new();
-    //         let decrement;
-    //         let Vertexs_len = self.Vertexs.len();
-
-    //         {
-    //             if let Some(ref mut Vertex) = self.Vertexs[Vertex_id] {
-    //                 decrement = 0 - (Vertex.refcount + 1);
-    //                 for relation in Vertex.relations.iter() {
-    //                     if let Some(rel_Vertex_id) = *relation {
-    //                         relations.push(rel_Vertex_id);
-    //                     }
-    //                 }
-                
-    //                 Vertex.relations.clear();
-
-    //                 if Vertex.refcount == 0 {
-    //                     // If nobody points to me, we can fully bail out
-    //                     full_remove = true;
-    //                 }else{
-    //                     // otherwise just remove the payload that we intend to remove
-    //                     Vertex.payload = None;
-    //                 }
-    //             }else{
-    //                 panic!("sanity error");
-    //             }
-
-    //             if full_remove {
-    //                 self.Vertexs[Vertex_id] = None;
-    //                 self.vacancies.push(Vertex_id);
-    //             }
-    //         }
-
-    //         // no payload means we're not pointing to these anymore, at least not within the context manager
-    //         for rel_Vertex_id in relations {
-    //             let mut removed = vec![false; Vertexs_len];
-    //             self.increment(rel_Vertex_id, decrement, &mut removed);
-    //         }
-
-    //     }
-
-    // }
-
-    // /// Creates or returns a DependencyGraph Vertex for a given key
-    // fn assert_Vertex(&mut self, key: K) -> VertexId where K: PartialEq, P: Clone {
-    //     if let Some(Vertex_id) = self.Vertexs.iter().position(|i| {
-    //         if let &Some(ref it) = i {
-    //             it.key == key
-    //         } else {
-    //             false
-    //         }
-    //     }) {
-    //         Vertex_id
-    //     } else {
-    //         let Vertex = Vertex::new(key, None);
-
-    //         if let Some(Vertex_id) = self.vacancies.pop() {
-    //             self.Vertexs[Vertex_id] = Some(Vertex);
-    //             Vertex_id
-    //         } else {
-    //             self.Vertexs.push(Some(Vertex));
-    //             self.Vertexs.len() - 1
-    //         }
-
-    //     }
-    // }
-
-    // fn set_relation(&mut self, Vertex_id: VertexId, dependency: RelationLink) {
-
-    //     // let Vertex = &self.Vertexs[Vertex_id];
-    //     // retrieve existing relation by SlotId as the vec offset
-    //     // Some(&Some()) due to empty vec slot vs None relation (logically equivalent)
-    //     let mut remove = None;
-    //     {
-    //         let Vertex = {
-    //             if let Some(ref Vertex) = self.Vertexs[Vertex_id] {
-    //                 Vertex
-    //             } else {
-    //                 panic!("sanity error. set relation on Vertex that does not exist")
-    //             }
-    //         };
-
-    //         if let Some(&Some(rel_Vertex_id)) = Vertex.relations.get(link.slot_id as usize) {
-    //             // relation exists
-
-    //             let decrement;
-    //             {
-    //                 if let &Some(ref rel_Vertex) = &self.Vertexs[rel_Vertex_id] {
-
-    //                     // no change. bail out. do not increment or decrement
-    //                     if Some(rel_Vertex.key) == link.key {
-    //                         return;
-    //                     }
-
-    //                     decrement = 0 - (1 + Vertex.refcount);
-    //                 } else {
-    //                     panic!("sanity error. relation Vertex_id located, but not found in Vertexs")
-    //                 }
-    //             }
-
-    //             remove = Some((rel_Vertex_id, decrement));
-    //         };
-    //     }
-
-
-    //     // ruh roh, we're different. Have to back out the old relation
-    //     // (a little friendly sparring with the borrow checker :-x )
-    //     if let Some((rel_Vertex_id, decrement)) = remove {
-    //         let mut removed = vec![false; self.Vertexs.len()];
-    //         {
-    //             self.increment(rel_Vertex_id, decrement, &mut removed)
-    //         };
-    //         // Vertex.relations[link.slot_id] MUST be set below
-    //     }
-
-    //     if let Some(key) = link.key {
-    //         let new_rel_Vertex_id = {
-    //             self.assert_Vertex(key)
-    //         };
-
-    //         let increment;
-    //         {
-    //             if let &mut Some(ref mut Vertex) = &mut self.Vertexs[Vertex_id] {
-    //                 while Vertex.relations.len() <= link.slot_id as usize { 
-    //                     Vertex.relations.push(None);
-    //                 }
-
-    //                 Vertex.relations[link.slot_id as usize] = Some(new_rel_Vertex_id);
-    //                 increment = 1 + Vertex.refcount;
-    //             } else {
-    //                 panic!("sanity error. relation just set")
-    //             }
-    //         };
-
-    //         let mut added = vec![false; self.Vertexs.len()];
-    //         self.increment(new_rel_Vertex_id, increment, &mut added);
-    //     } else {
-    //         // sometimes this will be unnecessary, but it's essential to overwrite a Some() if it's there
-    //         if let &mut Some(ref mut Vertex) = &mut self.Vertexs[Vertex_id] {
-    //             while Vertex.relations.len() <= link.slot_id as usize { 
-    //                 Vertex.relations.push(None);
-    //             }
-
-    //             Vertex.relations[link.slot_id as usize] = None;
-
-    //         } else {
-    //             panic!("sanity error. relation Vertex not found in Vertexs")
-    //         }
-    //     }
-    // }
-    // fn increment(&mut self, Vertex_id: VertexId, increment: isize, seen: &mut Vec<bool>) {
-    //     // Avoid traversing cycles
-    //     if Some(&true) == seen.get(Vertex_id) {
-    //         return; // dejavu! Bail out
-    //     }
-    //     seen[Vertex_id] = true;
-
-    //     let relations: Vec<VertexId>;
-
-    //     {
-    //         if let &mut Some(ref mut Vertex) = &mut self.Vertexs[Vertex_id] {
-    //             Vertex.refcount += increment;
-    //             assert!(Vertex.refcount >= 0,
-    //                     "sanity error. refcount below zero");
-
-    //             relations = Vertex.relations.iter().filter_map(|r| *r).collect();
-    //         } else {
-    //             panic!("sanity error. increment for Vertex_id");
-    //         }
-    //     };
-
-    //     for rel_Vertex_id in relations {
-    //         self.increment(rel_Vertex_id, increment, seen);
-    //     }
-
-    // }
+    /// Clones of the values for several keys, each None if not resident, under a single acquisition of the graph lock
+    pub fn get_many<Q,const N: usize>(&self, keys: [&Q; N]) -> [Option<V>; N] where K: Borrow<Q>, Q: ?Sized+PartialEq, V: Clone {
+        let vertex_vec = self.vertex_vec.lock().unwrap();
+        keys.map(|key| {
+            let vertex = Vertex::find(key, &vertex_vec)?;
+            let state = vertex.state.lock().unwrap();
+            match *state {
+                VertexState::Resident{ ref value, .. } => Some(value.clone()),
+                VertexState::Phantom => None,
+            }
+        })
+    }
+    /// Apply `f` to the value for a key, if resident, under the vertex lock. `f` must not call back into the graph.
+    ///
+    /// This, and `with_value_mut`, stand in for `Index` and `IndexMut`, which the graph doesn't implement: a reference
+    /// to the value can't outlive the lock on its vertex, whereas `graph[&key]` would have to return one.
+    pub fn get_with<R,F,Q>(&self, key: &Q, f: F) -> Option<R> where K: Borrow<Q>, Q: ?Sized+PartialEq, F: FnOnce(&V) -> R {
+        let vertex_vec = self.vertex_vec.lock().unwrap();
+        let vertex = Vertex::find(key, &vertex_vec)?;
+        let state = vertex.state.lock().unwrap();
+        match *state {
+            VertexState::Resident{ ref value, .. } => Some(f(value)),
+            VertexState::Phantom => None,
+        }
+    }
+    /// Mutate the value for a key in place, leaving its edges untouched. Returns the result of `f`, or None if the key
+    /// is not resident. As with `get_with`, `f` runs under the vertex lock and must not call back into the graph.
+    pub fn with_value_mut<R,F,Q>(&self, key: &Q, f: F) -> Option<R> where K: Borrow<Q>, Q: ?Sized+PartialEq, F: FnOnce(&mut V) -> R {
+        let vertex_vec = self.vertex_vec.lock().unwrap();
+        let vertex = Vertex::find(key, &vertex_vec)?;
+        let mut state = vertex.state.lock().unwrap();
+        match *state {
+            VertexState::Resident{ ref mut value, .. } => Some(f(value)),
+            VertexState::Phantom => None,
+        }
+    }
+}
+
+/// A resident vertex, as yielded by `TopoIter`
+#[derive(Clone,Debug,PartialEq)]
+pub struct TopoItem<K,V> {
+    pub key: K,
+    pub value: V,
+    /// The destination of each of its edges in edge order, phantom or not, and so including any it was ordered after
+    pub edges: Vec<K>,
+    /// As of when the iterator was created
+    pub status: VertexStatus,
+    /// Under `CyclePolicy::EmitSccAsGroup`, the index of the cycle this vertex is a member of, counting cycles in the
+    /// order they're yielded. Otherwise None
+    pub group: Option<usize>,
+}
+
+/// Where an item sits in the traversal, as yielded by `TopoIter::next_with_context`
+#[derive(Clone,Debug,PartialEq)]
+pub struct TraversalContext<K> {
+    /// Length of the longest chain of dependencies beneath this vertex. Zero for the initial frontier
+    pub depth: usize,
+    /// Direct dependencies which this iterator has already yielded
+    pub yielded_deps: Vec<K>,
+    /// Whether this vertex is part of a cycle, which the iteration order had to break
+    pub in_cycle: bool,
+}
+
+/// An item pending in a `TopoIter`, with the structure needed to steer iteration
+#[derive(Clone)]
+struct Ordered<K,V> {
+    item: TopoItem<K,V>,
+    slot: usize,
+    /// Distinct resident dependency slots, and their keys
+    deps: Vec<usize>,
+    dep_keys: Vec<K>,
+    depth: usize,
+    in_cycle: bool,
 }
 
+/// Iterates over a snapshot of the resident vertices taken when the iterator was created, dependencies first.
+/// Cloning the iterator clones the remaining snapshot, and does not touch the graph.
+#[derive(Clone)]
 pub struct TopoIter<K,V,E> {
-    visited: Vec<K>,
-    vertex_vec: Arc<Mutex<Vec<Arc<Vertex<K,V,E>>>>>
+    sorted: VecDeque<Ordered<K,V>>,
+    /// Slot of the most recently yielded item
+    current: Option<usize>,
+    /// Slots which have been pruned, or skipped as a consequence
+    pruned: Vec<usize>,
+    /// Slots which have been yielded
+    yielded: Vec<usize>,
+    measure: PhantomData<E>,
+}
+
+/// Adapts a `TopoIter` to yield each item with its `TraversalContext`
+pub struct WithContext<K,V,E> {
+    iter: TopoIter<K,V,E>,
+}
+
+impl<K,V,E> Iterator for WithContext<K,V,E> where K: Clone {
+    type Item = (TopoItem<K,V>, TraversalContext<K>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next_with_context()
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
 }
 
 impl <K,V,E> Iterator for TopoIter<K,V,E> {
-    type Item = Vertex<K,V,E>;
+    type Item = TopoItem<K,V>;
 
-    fn next(&mut self) -> Option<Vertex> {
-        self.sorted.pop()
+    fn next(&mut self) -> Option<TopoItem<K,V>> {
+        let ordered = self.sorted.pop_front()?;
+        self.current = Some(ordered.slot);
+        self.yielded.push(ordered.slot);
+        Some(ordered.item)
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.sorted.len(), Some(self.sorted.len()))
+    }
+}
+
+impl<K,V,E> ExactSizeIterator for TopoIter<K,V,E> {}
+
+/// Taking from the back yields the items last in the order, dependents before their dependencies. Items so taken count
+/// as yielded, and the last becomes the current item for `prune_current`, though none of its dependents remain.
+impl<K,V,E> DoubleEndedIterator for TopoIter<K,V,E> {
+    fn next_back(&mut self) -> Option<TopoItem<K,V>> {
+        let ordered = self.sorted.pop_back()?;
+        self.current = Some(ordered.slot);
+        self.yielded.push(ordered.slot);
+        Some(ordered.item)
     }
 }
 
 impl<K,V,E> TopoIter<K,V,E> {
-     fn new(vertex_vec: Arc<Mutex<Vec<Arc<Vertex<K,V,E>>>>>) -> Self {
+     fn new<U,F>(vertex_vec: &[Arc<Vertex<K,U,E>>], project: F) -> Self where K: Clone, F: FnMut(&U, &[Edge<K,U,E>]) -> V {
+         let adjacency = Adjacency::new(vertex_vec);
+         TopoIter::restricted(vertex_vec, &adjacency, &vec![true; vertex_vec.len()], project)
+     }
+
+     /// Iterate over only the included slots, ordered as they would be among the whole graph, with each item's value
+     /// obtained from the vertex's value and edges by `project`
+     fn restricted<U,F>(vertex_vec: &[Arc<Vertex<K,U,E>>], adjacency: &Adjacency, included: &[bool], project: F) -> Self
+         where K: Clone, F: FnMut(&U, &[Edge<K,U,E>]) -> V {
+         TopoIter::ordered(vertex_vec, adjacency, adjacency.topo_order(), included, project)
+     }
+
+     /// As `restricted`, in the given `order` of slots
+     fn ordered<U,F>(vertex_vec: &[Arc<Vertex<K,U,E>>], adjacency: &Adjacency, order: Vec<usize>, included: &[bool], mut project: F)
+         -> Self where K: Clone, F: FnMut(&U, &[Edge<K,U,E>]) -> V {
+
+         let mut in_cycle = vec![false; vertex_vec.len()];
+         for component in strongly_connected(&adjacency.out) {
+             if component.len() > 1 || adjacency.out[component[0]].contains(&component[0]) {
+                 for i in component {
+                     in_cycle[i] = true;
+                 }
+             }
+         }
+
+         let mut depth: Vec<Option<usize>> = vec![None; vertex_vec.len()];
+         let sorted = order.into_iter().filter(|&i| included[i]).filter_map(|i| {
+             let key = vertex_vec[i].key.lock().unwrap().clone()?;
+             let (value, edges) = match *vertex_vec[i].state.lock().unwrap() {
+                 VertexState::Resident{ ref value, ref edges } => {
+                     (project(value, edges), edges.iter().filter_map(|e| e.dest_vertex.key.lock().unwrap().clone()).collect())
+                 },
+                 VertexState::Phantom => return None,
+             };
+             let deps: Vec<usize> = adjacency.dependencies(i).into_iter().filter(|&j| adjacency.active(j) && included[j]).collect();
+             let dep_keys = deps.iter().filter_map(|&j| vertex_vec[j].key.lock().unwrap().clone()).collect();
+
+             // Dependencies ordered later (within a cycle) don't count toward depth
+             depth[i] = Some(deps.iter().filter_map(|&j| depth[j]).max().map_or(0, |d| d + 1));
+             Some(Ordered{
+                 item: TopoItem{ key, value, edges, status: vertex_vec[i].status(), group: None },
+                 slot: i,
+                 deps,
+                 dep_keys,
+                 depth: depth[i].unwrap(),
+                 in_cycle: in_cycle[i],
+             })
+         }).collect();
+
          TopoIter{
-             visited: Vec::new(),
-             vertex_vec: vertex_vec
+             sorted,
+             current: None,
+             pruned: Vec::new(),
+             yielded: Vec::new(),
+             measure: PhantomData,
          }
      }
+
+     /// As `next`, additionally returning the item's `TraversalContext`
+     pub fn next_with_context(&mut self) -> Option<(TopoItem<K,V>, TraversalContext<K>)> where K: Clone {
+         let ordered = self.sorted.pop_front()?;
+         let yielded_deps = ordered.deps.iter().zip(ordered.dep_keys.iter())
+             .filter(|&(d, _)| self.yielded.contains(d))
+             .map(|(_, k)| k.clone())
+             .collect();
+
+         self.current = Some(ordered.slot);
+         self.yielded.push(ordered.slot);
+         Some((ordered.item, TraversalContext{
+             depth: ordered.depth,
+             yielded_deps,
+             in_cycle: ordered.in_cycle,
+         }))
+     }
+
+     /// Yield each item together with its `TraversalContext`
+     pub fn with_context(self) -> WithContext<K,V,E> {
+         WithContext{ iter: self }
+     }
+
+     /// Look ahead at up to the next `n` items, without consuming them
+     pub fn peek_n(&self, n: usize) -> Vec<&TopoItem<K,V>> {
+         self.sorted.iter().take(n).map(|o| &o.item).collect()
+     }
+
+     /// Fast-forward past everything ordered before `key`, such that it is the next item yielded.
+     /// The remainder retains its topological order. Returns false, without skipping anything, if `key` is not pending.
+     pub fn skip_to(&mut self, key: &K) -> bool where K: PartialEq {
+         match self.sorted.iter().position(|o| o.item.key == *key) {
+             Some(i) => {
+                 self.sorted.drain(..i);
+                 true
+             },
+             None => false,
+         }
+     }
+
+     /// Don't yield anything which depends solely on the item just yielded, whether directly or via other such items.
+     /// Vertices with some other dependency are still yielded. Returns the keys which will now be skipped.
+     pub fn prune_current(&mut self) -> Vec<K> where K: Clone {
+         let current = match self.current {
+             Some(current) => current,
+             None => return Vec::new(),
+         };
+         if !self.pruned.contains(&current) {
+             self.pruned.push(current);
+         }
+
+         // Dependencies precede their dependents, so a single pass catches transitive pruning
+         let mut skipped = Vec::new();
+         let pruned = &mut self.pruned;
+         self.sorted.retain(|o| {
+             if !o.deps.is_empty() && o.deps.iter().all(|d| pruned.contains(d)) {
+                 pruned.push(o.slot);
+                 skipped.push(o.item.key.clone());
+                 false
+             }else{
+                 true
+             }
+         });
+         skipped
+     }
 }
-//         // TODO: make this respond to context changes while we're mid-iteration.
-//         // Approach A: switch Vec<Vertex> to Arc<Vec<Option<Vertex>>> and avoid slot reclamation until the iter is complete
-//         // Approach B: keep Vec<Vertex> sorted (DESC) by refcount, and reset the increment whenever the sort changes
-
-//         // FOR now, taking the low road
-//         // Vec<(usize, MemoRefpayload, Vec<K>)>
-//         let mut subject_payloads: Vec<Subjectpayload> = Vertexs.iter()
-//             .filter_map(|i| {
-//                 if let &Some(ref Vertex) = i {
-//                     if let Some(ref payload) = Vertex.payload {
-
-//                         let relation_keys: Vec<K> = Vertex.relations
-//                             .iter()
-//                             .filter_map(|maybe_Vertex_id| {
-//                                 if let &Some(Vertex_id) = maybe_Vertex_id {
-//                                     if let Some(ref Vertex) = Vertexs[Vertex_id] {
-//                                         Some(Vertex.key)
-//                                     } else {
-//                                         panic!("sanity error, subject_payload_iter")
-//                                     }
-//                                 } else {
-//                                     None
-//                                 }
-//                             })
-//                             .collect();
-
-//                         return Some(Subjectpayload {
-//                             key: Vertex.key,
-//                             refcount: Vertex.refcount as usize,
-//                             value: V.clone(),
-//                             from_keys: vec![],
-//                             to_keys: relation_keys,
-//                         });
-//                     }
-//                 }
-//                 None
-//             })
-//             .collect();
-
-//         // Ascending sort here, because the iterator is using pop
-//         // TODO: be sure to reverse this later if we switch to incremental calculation
-//         subject_payloads.sort_by(|a, b| a.refcount.cmp(&b.refcount));
-
-//         SubjectpayloadIter { sorted: subject_payloads }
-//     }
-// }
 
 #[cfg(test)]
 mod test {
-    use std::collections::HashMap;
     use super::DependencyGraph;
 
     #[test]
     fn basic() {
-        let mut graph = DependencyGraph::new();
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
         graph.insert("A", "Alpha",   vec![]);
-        graph.insert("B", "Bravo",   vec![("A",None)];
-        graph.insert("C", "Charlie", vec![("B",None)];
-        graph.insert("D", "Delta",   vec![("C",None)];
+        graph.insert("B", "Bravo",   vec![("A",None)]);
+        graph.insert("C", "Charlie", vec![("B",None)]);
+        graph.insert("D", "Delta",   vec![("C",None)]);
 
         let mut iter = graph.iter();
         assert_eq!("A", iter.next().expect("should be present").key);
@@ -459,8 +1120,61 @@ mod test {
     }
 
     #[test]
-    fn belated() {
+    fn iter_rev() {
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+        graph.insert("A", "Alpha",   vec![("D",None)]);
+        graph.insert("B", "Bravo",   vec![("A",None),("Z",None)]);
+        graph.insert("C", "Charlie", vec![("B",None)]);
+        graph.insert("D", "Delta",   vec![]);
+
+        let keys: Vec<_> = graph.iter_rev().map(|i| i.key).collect();
+        assert_eq!(keys, vec!["C","B","A","D"]);
+
+        let mut iter = graph.iter_rev();
+        iter.next();
+        assert_eq!(iter.prune_current(), vec!["B","A","D"], "each depended on only by what was pruned");
+    }
+
+    #[test]
+    fn double_ended() {
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+        graph.insert("A", "Alpha",   vec![]);
+        graph.insert("B", "Bravo",   vec![("A",None)]);
+        graph.insert("C", "Charlie", vec![("B",None)]);
+        graph.insert("D", "Delta",   vec![("C",None)]);
+
+        let mut iter = graph.iter();
+        assert_eq!(iter.next().map(|i| i.key), Some("A"));
+        assert_eq!(iter.next_back().map(|i| i.key), Some("D"));
+        assert_eq!(iter.len(), 2);
+        assert_eq!(iter.rev().map(|i| i.key).collect::<Vec<_>>(), vec!["C","B"]);
+    }
+
+    #[test]
+    fn item_edges() {
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+        graph.insert("A", "Alpha", vec![]);
+        graph.insert("B", "Bravo", vec![("Z",None),("A",None),("B",None)]);
+
+        let items: Vec<_> = graph.iter().map(|i| (i.key, i.edges)).collect();
+        assert_eq!(items, vec![("A",vec![]),("B",vec!["Z","A","B"])]);
+        let items: Vec<_> = graph.iter_rev().map(|i| (i.key, i.edges)).collect();
+        assert_eq!(items, vec![("B",vec!["Z","A","B"]),("A",vec![])]);
+    }
+
+    #[test]
+    fn iter_with_edges() {
         let mut graph = DependencyGraph::new();
+        graph.insert("A", "Alpha", vec![]);
+        graph.insert("B", "Bravo", vec![("A",Some(3)),("Z",None)]);
+
+        let items: Vec<_> = graph.iter_with_edges().map(|i| i.value).collect();
+        assert_eq!(items, vec![("Alpha",vec![]),("Bravo",vec![("A",Some(3)),("Z",None)])]);
+    }
+
+    #[test]
+    fn belated() {
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
         graph.insert("A", "Alpha",   vec![("D",None)]);
         graph.insert("B", "Bravo",   vec![("A",None)]);
         graph.insert("C", "Charlie", vec![("B",None)]);
@@ -476,7 +1190,7 @@ mod test {
 
     #[test]
     fn dual_indegree_zero() {
-        let mut graph = DependencyGraph::new();
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
         graph.insert("A", "Alpha",   vec![]);
         graph.insert("B", "Bravo",   vec![("A",None)]);
         graph.insert("C", "Charlie", vec![]);
@@ -492,10 +1206,29 @@ mod test {
         assert!(iter.next().is_none(), "should have ended");
     }
 
+    #[test]
+    fn by_key() {
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+        graph.insert("A", "Alpha",   vec![]);
+        graph.insert("B", "Bravo",   vec![("A",None)]);
+        graph.insert("C", "Charlie", vec![]);
+        graph.insert("D", "Delta",   vec![("C",None)]);
+
+        // Unlike iter, which goes CADB, this picks the least key ready
+        let keys: Vec<_> = graph.iter_by_key().map(|i| i.key).collect();
+        assert_eq!(keys, vec!["A","B","C","D"]);
+        graph.insert("E", "Echo", vec![]);
+        graph.insert("A", "Alpha", vec![("E",None)]);
+        let keys: Vec<_> = graph.iter_by_key().map(|i| i.key).collect();
+        assert_eq!(keys, vec!["C","D","E","A","B"]);
+        graph.insert("C", "Charlie", vec![("D",None)]);
+        assert_eq!(graph.iter_by_key().count(), 5, "cycles are broken");
+    }
+
     #[test]
     fn repoint_relation() {
 
-        let mut graph = DependencyGraph::new();
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
         // B -> A
         // D -> C
         // Then:
@@ -519,7 +1252,7 @@ mod test {
     #[test]
     fn remove() {
 
-        let mut graph = DependencyGraph::new();
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
         graph.insert("A", "Alpha",   vec![]);
         graph.insert("B", "Bravo",   vec![("A",None)]);
         graph.insert("C", "Charlie", vec![("B",None)]);
@@ -536,4 +1269,537 @@ mod test {
         assert_eq!("A", iter.next().expect("should be present").key);
         assert!(iter.next().is_none(), "should have ended");
     }
+
+    #[test]
+    fn cycle() {
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+        // A <-> B, with A depending on X, and C on A
+        graph.insert("X", "X-ray",   vec![]);
+        graph.insert("A", "Alpha",   vec![("B",None),("X",None)]);
+        graph.insert("B", "Bravo",   vec![("A",None)]);
+        graph.insert("C", "Charlie", vec![("A",None)]);
+
+        // The cycle is broken at A, having the most incoming edges, and is still ordered after X and before C
+        let keys: Vec<_> = graph.iter().map(|i| i.key).collect();
+        assert_eq!(keys, vec!["X","A","B","C"]);
+    }
+
+    #[test]
+    fn peek_and_clone() {
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+        graph.insert("A", "Alpha",   vec![]);
+        graph.insert("B", "Bravo",   vec![("A",None)]);
+        graph.insert("C", "Charlie", vec![("B",None)]);
+
+        let mut iter = graph.iter();
+        let peeked: Vec<&str> = iter.peek_n(2).iter().map(|i| i.key).collect();
+        assert_eq!(peeked, vec!["A","B"]);
+        assert_eq!(iter.peek_n(5).len(), 3);
+
+        assert_eq!("A", iter.next().expect("should be present").key);
+        let snapshot = iter.clone();
+
+        // Later changes are not reflected in either iterator
+        graph.insert("D", "Delta", vec![("C",None)]);
+        let rest: Vec<&str> = iter.map(|i| i.key).collect();
+        assert_eq!(rest, vec!["B","C"]);
+        let rest: Vec<&str> = snapshot.map(|i| i.key).collect();
+        assert_eq!(rest, vec!["B","C"]);
+    }
+
+    #[test]
+    fn skip_to() {
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+        graph.insert("A", "Alpha",   vec![]);
+        graph.insert("B", "Bravo",   vec![("A",None)]);
+        graph.insert("C", "Charlie", vec![("B",None)]);
+        graph.insert("D", "Delta",   vec![("C",None)]);
+
+        let mut iter = graph.iter();
+        assert!(!iter.skip_to(&"Z"));
+        assert!(iter.skip_to(&"C"));
+        assert_eq!("C", iter.next().expect("should be present").key);
+        assert!(!iter.skip_to(&"B"), "already passed");
+        assert_eq!("D", iter.next().expect("should be present").key);
+        assert!(iter.next().is_none(), "should have ended");
+    }
+
+    #[test]
+    fn prune_current() {
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+        // B, and through it C, depend solely on A. D also depends on X
+        graph.insert("A", "Alpha",   vec![]);
+        graph.insert("X", "X-ray",   vec![]);
+        graph.insert("B", "Bravo",   vec![("A",None)]);
+        graph.insert("C", "Charlie", vec![("B",None),("A",None)]);
+        graph.insert("D", "Delta",   vec![("A",None),("X",None)]);
+
+        let mut iter = graph.iter();
+        assert!(iter.prune_current().is_empty(), "nothing yielded yet");
+
+        let mut yielded = Vec::new();
+        while let Some(item) = iter.next() {
+            if item.key == "A" {
+                assert_eq!(iter.prune_current(), vec!["B","C"]);
+            }
+            yielded.push(item.key);
+        }
+        yielded.sort();
+        assert_eq!(yielded, vec!["A","D","X"]);
+    }
+
+    #[test]
+    fn traversal_context() {
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+        // B <-> C form a cycle, atop A, with D depending on both
+        graph.insert("A", "Alpha",   vec![]);
+        graph.insert("B", "Bravo",   vec![("A",None),("C",None)]);
+        graph.insert("C", "Charlie", vec![("B",None)]);
+        graph.insert("D", "Delta",   vec![("B",None),("C",None),("Z",None)]);
+
+        let mut iter = graph.iter();
+        iter.skip_to(&"D");
+        let (item, context) = iter.next_with_context().expect("should be present");
+        assert_eq!(item.key, "D");
+        assert_eq!(context.depth, 3);
+        assert!(context.yielded_deps.is_empty(), "dependencies were skipped, not yielded");
+        assert!(!context.in_cycle);
+
+        let contexts: Vec<_> = graph.iter().with_context().map(|(item, context)| (item.key, context)).collect();
+        assert_eq!(contexts[0].0, "A");
+        assert_eq!(contexts[0].1.depth, 0);
+        assert!(!contexts[0].1.in_cycle);
+        assert!(contexts[1].1.in_cycle && contexts[2].1.in_cycle);
+        assert_eq!(contexts[1].1.yielded_deps, vec!["A"]);
+        assert_eq!(contexts[2].1.depth, 2);
+        assert_eq!(contexts[3].1.yielded_deps, vec!["B","C"]);
+    }
+
+    #[test]
+    fn insert_outcome() {
+        use super::InsertKind;
+
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+        let outcome = graph.insert("B", "Bravo", vec![("A",None),("C",None),("A",None)]);
+        assert_eq!(outcome.kind, InsertKind::Created);
+        assert_eq!(outcome.created_phantoms, vec!["A","C"]);
+        assert_eq!(outcome.previous, None);
+
+        let outcome = graph.insert("A", "Alpha", vec![("C",None),("D",None)]);
+        assert_eq!(outcome.kind, InsertKind::Promoted);
+        assert_eq!(outcome.created_phantoms, vec!["D"]);
+
+        let outcome = graph.insert("A", "Alpha Prime", vec![("A",None)]);
+        assert_eq!(outcome.kind, InsertKind::Updated);
+        assert!(outcome.created_phantoms.is_empty());
+        assert_eq!(outcome.previous, Some(("Alpha", vec![("C",None),("D",None)])));
+
+        // D was only referenced by A's previous edges, and so is gone
+        let outcome = graph.insert("E", "Echo", vec![("D",None)]);
+        assert_eq!(outcome.created_phantoms, vec!["D"]);
+    }
+
+    #[test]
+    fn insert_previous() {
+        let mut graph = DependencyGraph::new();
+        assert_eq!(graph.insert("A", 1, vec![("B",Some(0.5)),("Z",None)]).previous, None);
+        assert_eq!(graph.insert("B", 2, vec![]).previous, None, "promoting a phantom has nothing to return");
+
+        let outcome = graph.insert("A", 10, vec![("B",Some(2.0))]);
+        assert_eq!(outcome.previous, Some((1, vec![("B",Some(0.5)),("Z",None)])), "the previous edges in order, measures included");
+        assert_eq!(graph.get(&"A"), Some(10));
+        assert_eq!(graph.edges(&"A"), vec![("B",Some(2.0))]);
+        assert!(!graph.contains_key(&"Z"), "released with the previous edges");
+    }
+
+    #[test]
+    fn replace_contents() {
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+        graph.insert("A", "Alpha", vec![]);
+        let reader = graph.clone();
+
+        let mut rebuilt = DependencyGraph::new();
+        rebuilt.insert("B", "Bravo",   vec![]);
+        rebuilt.insert("C", "Charlie", vec![("B",None)]);
+        let previous = graph.replace_contents(rebuilt);
+
+        let keys: Vec<&str> = reader.iter().map(|i| i.key).collect();
+        assert_eq!(keys, vec!["B","C"]);
+        let keys: Vec<&str> = previous.iter().map(|i| i.key).collect();
+        assert_eq!(keys, vec!["A"]);
+
+        // Replacing a graph with itself changes nothing
+        assert_eq!(graph.replace_contents(reader.clone()).iter().count(), 0);
+        assert_eq!(reader.iter().count(), 2);
+    }
+
+    #[test]
+    fn get() {
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+        graph.insert("A", "Alpha".to_string(), vec![("Z",None)]);
+
+        assert_eq!(graph.get(&"A"), Some("Alpha".to_string()));
+        assert_eq!(graph.get(&"Z"), None, "phantoms have no value");
+        assert_eq!(graph.get(&"B"), None);
+        assert_eq!(graph.get_with(&"A", |v| v.len()), Some(5));
+
+        assert_eq!(graph.with_value_mut(&"A", |v| { v.push_str(" Prime"); v.len() }), Some(11));
+        assert_eq!(graph.with_value_mut(&"Z", |v| v.clear()), None);
+        assert_eq!(graph.get(&"A"), Some("Alpha Prime".to_string()));
+        assert_eq!(graph.distance_map(&"A").len(), 2, "edges are untouched");
+    }
+
+    #[test]
+    fn with_value_mut() {
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+        graph.insert("A", vec![1], vec![]);
+        graph.insert("B", vec![2], vec![("A",None),("Z",None)]);
+
+        assert_eq!(graph.with_value_mut(&"B", |v| { v.push(3); v.len() }), Some(2));
+        assert_eq!(graph.get(&"B"), Some(vec![2,3]));
+        assert_eq!(graph.with_value_mut(&"Z", |v| v.push(0)), None, "phantoms have no value to mutate");
+        assert_eq!(graph.with_value_mut(&"Y", |v| v.push(0)), None);
+
+        let items: Vec<_> = graph.iter().map(|i| (i.key, i.edges)).collect();
+        assert_eq!(items, vec![("A",vec![]),("B",vec!["A","Z"])], "neither edges nor order are affected");
+    }
+
+    #[test]
+    fn contains() {
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+        graph.insert("A", "Alpha", vec![("Z",None)]);
+        graph.insert("B", "Bravo", vec![]);
+        graph.mask(&"B");
+
+        assert!(graph.contains_key(&"A") && graph.contains_resident(&"A"));
+        assert!(graph.contains_key(&"Z") && !graph.contains_resident(&"Z"), "phantoms are present but not resident");
+        assert!(graph.contains_key(&"B") && graph.contains_resident(&"B"), "masked vertices are still resident");
+        assert!(!graph.contains_key(&"C") && !graph.contains_resident(&"C"));
+
+        graph.remove("A");
+        assert!(!graph.contains_key(&"A"), "nothing refers to A once removed");
+        assert!(!graph.contains_key(&"Z"), "nor to Z, once A's edges are released");
+    }
+
+    #[test]
+    fn status() {
+        use super::VertexStatus;
+
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+        graph.insert("A", "Alpha", vec![]);
+        graph.insert("B", "Bravo", vec![("A",None),("Z",None)]);
+        graph.insert("C", "Charlie", vec![]);
+        graph.mask(&"C");
+
+        assert_eq!(graph.status(&"A"), VertexStatus::Resident);
+        assert_eq!(graph.status(&"Z"), VertexStatus::Phantom);
+        assert_eq!(graph.status(&"C"), VertexStatus::Masked);
+        assert_eq!(graph.status(&"D"), VertexStatus::Missing);
+        assert!(graph.contains_key(&"Z") && !graph.contains_resident(&"Z"));
+        assert!(graph.contains_key(&"C") && graph.contains_resident(&"C"));
+        assert!(!graph.contains_key(&"D"));
+        assert!(graph.iter().all(|i| i.status == VertexStatus::Resident));
+
+        // A is still referenced by B once drained, whereas B is not referenced at all
+        let drained: Vec<_> = graph.drain_topo().take(2).map(|(k, _)| k).collect();
+        assert_eq!(drained, vec!["A","B"]);
+        assert_eq!(graph.status(&"A"), VertexStatus::Missing, "no longer referenced, once B was drained too");
+        assert_eq!(graph.status(&"B"), VertexStatus::Missing);
+
+        graph.insert("D", "Delta", vec![("E",None)]);
+        graph.insert("E", "Echo", vec![]);
+        graph.drain_topo().next();
+        assert_eq!(graph.status(&"E"), VertexStatus::Removed);
+        graph.insert("E", "Echo", vec![]);
+        assert_eq!(graph.status(&"E"), VertexStatus::Resident);
+    }
+
+    #[test]
+    fn missing_status() {
+        use super::VertexStatus;
+
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+        assert_eq!(graph.status(&"A"), VertexStatus::Missing);
+
+        graph.insert("B", "Bravo", vec![("A",None)]);
+        assert_eq!(graph.status(&"A"), VertexStatus::Phantom, "referenced, and so no longer missing");
+        graph.insert("A", "Alpha", vec![]);
+        graph.remove("A");
+        assert_eq!(graph.status(&"A"), VertexStatus::Removed, "still referenced by B");
+        graph.remove("B");
+        assert_eq!(graph.status(&"A"), VertexStatus::Missing);
+        assert_eq!(graph.status(&"B"), VertexStatus::Missing);
+    }
+
+    #[test]
+    fn keys_and_values() {
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+        graph.insert("B", "Bravo", vec![("A",None),("Z",None)]);
+        graph.insert("A", "Alpha", vec![]);
+
+        let mut keys: Vec<_> = graph.keys().collect();
+        keys.sort();
+        assert_eq!(keys, vec!["A","B"]);
+        let pairs: Vec<_> = graph.keys().zip(graph.values()).collect();
+        assert!(pairs.contains(&("A","Alpha")) && pairs.contains(&("B","Bravo")));
+    }
+
+    #[test]
+    fn counts() {
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+        assert!(graph.is_empty());
+        graph.insert("A", "Alpha", vec![("Y",None),("Z",None)]);
+        graph.insert("B", "Bravo", vec![("A",None),("Z",None),("B",None)]);
+        assert_eq!((graph.len(), graph.phantom_count(), graph.edge_count()), (2, 2, 5));
+
+        graph.insert("Z", "Zulu", vec![]);
+        graph.insert("A", "Alpha", vec![]);
+        assert_eq!((graph.len(), graph.phantom_count(), graph.edge_count()), (3, 0, 3));
+
+        // Including B, whose edge to itself is dropped along with it
+        assert_eq!(graph.drain_topo().count(), 3);
+        assert!(graph.is_empty());
+        assert_eq!((graph.phantom_count(), graph.edge_count()), (0, 0));
+
+        graph.insert("C", "Charlie", vec![("X",None)]);
+        let previous = graph.replace_contents(DependencyGraph::new());
+        assert!(graph.is_empty());
+        assert_eq!((previous.len(), previous.phantom_count(), previous.edge_count()), (1, 1, 1));
+    }
+
+    #[test]
+    fn topo_order_of() {
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+        graph.insert("D", "Delta",   vec![("C",None)]);
+        graph.insert("C", "Charlie", vec![("B",None)]);
+        graph.insert("B", "Bravo",   vec![("A",None)]);
+        graph.insert("A", "Alpha",   vec![("Z",None)]);
+        graph.insert("E", "Echo",    vec![]);
+
+        // Ordered by way of B and C, which aren't among them
+        assert_eq!(graph.topo_order_of(&["D","Z","A","Q"]), vec!["A","D"]);
+        assert_eq!(graph.topo_order_of(&["E"]), vec!["E"]);
+    }
+
+    #[test]
+    fn iter_from() {
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+        graph.insert("A", "Alpha",   vec![]);
+        graph.insert("B", "Bravo",   vec![("A",None)]);
+        graph.insert("C", "Charlie", vec![("B",None),("Z",None)]);
+        graph.insert("D", "Delta",   vec![("C",None)]);
+        graph.insert("E", "Echo",    vec![("A",None)]);
+        graph.insert("F", "Foxtrot", vec![]);
+
+        let keys: Vec<&str> = graph.iter_from(&["C","F"]).map(|i| i.key).collect();
+        assert_eq!(keys, vec!["A","B","C","F"]);
+
+        graph.mask(&"B");
+        let keys: Vec<&str> = graph.iter_from(&["C","E"]).map(|i| i.key).collect();
+        assert_eq!(keys, vec!["A","C","E"], "A is still required by E");
+        assert_eq!(graph.iter_from(&["B","Z"]).count(), 0);
+    }
+
+    #[test]
+    fn take() {
+        use super::VertexStatus;
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+        graph.insert("A", vec![1,2,3], vec![("Z",None)]);
+        graph.insert("B", vec![4], vec![("A",None)]);
+
+        assert_eq!(graph.take(&"A"), Some(vec![1,2,3]));
+        assert_eq!(graph.take(&"A"), None);
+        assert_eq!(graph.status(&"A"), VertexStatus::Removed, "still referenced by B");
+        assert_eq!(graph.status(&"Z"), VertexStatus::Missing, "only referenced by A");
+
+        assert_eq!(graph.take(&"B"), Some(vec![4]));
+        assert_eq!(graph.status(&"A"), VertexStatus::Missing);
+        assert!(graph.is_empty());
+        assert_eq!((graph.phantom_count(), graph.edge_count()), (0, 0));
+    }
+
+    #[test]
+    fn iter_dependents_of() {
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+        graph.insert("A", "Alpha",   vec![("Z",None)]);
+        graph.insert("B", "Bravo",   vec![("A",None)]);
+        graph.insert("C", "Charlie", vec![("B",None),("A",None)]);
+        graph.insert("D", "Delta",   vec![("C",None)]);
+        graph.insert("E", "Echo",    vec![]);
+
+        let keys: Vec<&str> = graph.iter_dependents_of(&"B").map(|i| i.key).collect();
+        assert_eq!(keys, vec!["C","D"]);
+        let keys: Vec<&str> = graph.iter_dependents_of(&"Z").map(|i| i.key).collect();
+        assert_eq!(keys, vec!["A","B","C","D"]);
+        assert_eq!(graph.iter_dependents_of(&"D").count(), 0);
+    }
+
+    #[test]
+    fn clear() {
+        use super::VertexStatus;
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+        graph.insert("A", "Alpha", vec![("A",None),("Z",None)]);
+        graph.insert("B", "Bravo", vec![("A",None)]);
+        let drain = graph.drain_topo();
+        let shared = graph.clone();
+
+        graph.clear();
+        assert!(shared.is_empty());
+        assert_eq!((shared.phantom_count(), shared.edge_count()), (0, 0));
+        assert_eq!(shared.status(&"Z"), VertexStatus::Missing);
+        assert_eq!(drain.count(), 0);
+
+        graph.insert("A", "Alpha", vec![]);
+        assert_eq!(shared.len(), 1);
+    }
+
+    #[test]
+    fn upsert() {
+        use super::InsertKind;
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+        let merge = |old: &mut Vec<u8>, new: Vec<u8>| old.extend(new);
+        assert_eq!(graph.upsert("A", vec![1], vec![("Z",None)], merge).kind, InsertKind::Created);
+
+        let outcome = graph.upsert("A", vec![2,3], vec![], merge);
+        assert_eq!((outcome.kind, outcome.previous), (InsertKind::Updated, None));
+        assert_eq!(graph.get(&"A"), Some(vec![1,2,3]));
+        assert!(!graph.contains_key(&"Z"), "edges are replaced");
+    }
+
+    #[test]
+    fn drain() {
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+        graph.insert("A", "Alpha", vec![("A",None),("Z",None)]);
+        graph.insert("B", "Bravo", vec![("A",None)]);
+        let shared = graph.clone();
+
+        let mut drained: Vec<_> = graph.drain().collect();
+        drained.sort();
+        assert_eq!(drained, vec![("A","Alpha"),("B","Bravo")]);
+        assert!(shared.is_empty());
+        assert_eq!((shared.phantom_count(), shared.edge_count()), (0, 0));
+
+        graph.insert("C", "Charlie", vec![("Y",None)]);
+        assert_eq!((shared.len(), shared.phantom_count(), shared.edge_count()), (1, 1, 1));
+    }
+
+    #[test]
+    fn deep_clone() {
+        let mut graph = DependencyGraph::new();
+        graph.insert("A", "Alpha", vec![("A",Some(1)),("Z",None)]);
+        graph.insert("B", "Bravo", vec![("A",Some(2))]);
+        graph.mask(&"B");
+        graph.pin(&"A");
+
+        let mut copy = graph.deep_clone();
+        assert!(copy == graph);
+        assert!(copy.is_masked(&"B") && copy.is_pinned(&"A"));
+        assert_eq!((copy.len(), copy.phantom_count(), copy.edge_count()), (2, 1, 3));
+
+        copy.insert("C", "Charlie", vec![]);
+        copy.remove("A");
+        assert!(graph.contains_resident(&"A") && !graph.contains_key(&"C"), "the original is unaffected");
+        assert_eq!(graph.edge_count(), 3);
+    }
+
+    #[test]
+    fn borrowed_keys() {
+        let mut graph: DependencyGraph<String,_,()> = DependencyGraph::new();
+        graph.insert("A".to_string(), 1, vec![("Z".to_string(),None)]);
+
+        assert_eq!(graph.get("A"), Some(1));
+        assert!(graph.contains_key("Z") && !graph.contains_resident("Z"));
+        assert!(graph.contains_edge("A", "Z"));
+        assert_eq!(graph.take("A"), Some(1));
+        assert!(graph.is_empty());
+    }
+
+    #[test]
+    fn unordered_keys() {
+        #[derive(Clone,Debug,PartialEq)]
+        struct Id(f64);
+
+        let mut graph: DependencyGraph<Id,_,()> = DependencyGraph::new();
+        graph.insert(Id(1.0), "Alpha", vec![(Id(0.5),None)]);
+        graph.insert(Id(0.5), "Bravo", vec![]);
+        let keys: Vec<Id> = graph.iter().map(|i| i.key).collect();
+        assert_eq!(keys, vec![Id(0.5), Id(1.0)]);
+    }
+
+    #[test]
+    fn iter_map() {
+        struct Handle(&'static str);
+
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+        graph.insert("A", Handle("alpha"), vec![]);
+        graph.insert("B", Handle("bravo"), vec![("A",None)]);
+        let names: Vec<&str> = graph.iter_map(|handle| handle.0).map(|i| i.value).collect();
+        assert_eq!(names, vec!["alpha","bravo"]);
+    }
+
+    #[test]
+    fn insert_batch() {
+        use super::InsertKind;
+
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+        let outcomes = graph.insert_batch(vec![
+            ("B", "Bravo", vec![("A",None)]),
+            ("A", "Alpha", vec![]),
+            ("B", "Bravo Prime", vec![]),
+        ]);
+        let kinds: Vec<InsertKind> = outcomes.iter().map(|o| o.kind).collect();
+        assert_eq!(kinds, vec![InsertKind::Created, InsertKind::Promoted, InsertKind::Updated]);
+        assert_eq!(outcomes[2].previous, Some(("Bravo", vec![("A",None)])));
+        assert_eq!(graph.len(), 2);
+    }
+
+    #[test]
+    fn capacity() {
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::with_capacity(2);
+        graph.insert("A", "Alpha", vec![("Z",None)]);
+        graph.insert("B", "Bravo", vec![]);
+        graph.reserve(100);
+        assert!(graph.capacity() >= 103);
+
+        graph.remove("A");
+        graph.remove("B");
+        graph.shrink_to_fit();
+        assert_eq!(graph.capacity(), 0);
+        assert!(!graph.contains_key(&"Z"));
+        graph.insert("C", "Charlie", vec![]);
+        assert_eq!(graph.keys().collect::<Vec<_>>(), vec!["C"]);
+    }
+
+    #[test]
+    fn phantom_keys() {
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+        graph.insert("A", "Alpha", vec![("Y",None),("Z",None)]);
+        graph.insert("B", "Bravo", vec![("A",None),("Z",None)]);
+        graph.insert("Y", "Yankee", vec![]);
+        graph.remove("A");
+
+        assert_eq!(graph.phantom_keys().collect::<Vec<_>>(), vec!["Z"]);
+    }
+
+    #[test]
+    fn resolve() {
+        use super::{InsertKind,ResolveError};
+
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+        graph.insert("B", "Bravo", vec![("A",None)]);
+
+        assert_eq!(graph.resolve("A", "Alpha", vec![]).map(|o| o.kind), Ok(InsertKind::Promoted));
+        assert_eq!(graph.resolve("A", "Alpha Prime", vec![]).map(|o| o.kind), Err(ResolveError::AlreadyResident));
+        assert_eq!(graph.get(&"A"), Some("Alpha"));
+        assert_eq!(graph.resolve("C", "Charlie", vec![]).map(|o| o.kind), Ok(InsertKind::Created));
+    }
+
+    #[test]
+    fn get_many() {
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+        graph.insert("A", "Alpha", vec![("Z",None)]);
+        graph.insert("B", "Bravo", vec![]);
+
+        assert_eq!(graph.get_many([&"B", &"Z", &"A", &"Y"]), [Some("Bravo"), None, Some("Alpha"), None]);
+    }
 }
@@ -0,0 +1,140 @@
+//! Guardrails on the size of a graph, for graphs fed from untrusted input.
+//!
+//! Every edge to an unknown key creates a phantom, so without limits a source of records can grow the graph without bound.
+
+use super::{Counts,DependencyGraph,Vertex,VertexVec};
+
+/// Limits enforced by `try_insert` (and `insert`, which panics instead). None means unlimited, which is the default.
+#[derive(Clone,Copy,Debug,Default,PartialEq,Eq)]
+pub struct Limits {
+    /// Resident and phantom vertices combined
+    pub max_vertices: Option<usize>,
+    pub max_phantoms: Option<usize>,
+    /// Edges of a single vertex, including any later added to it
+    pub max_edges_per_vertex: Option<usize>,
+}
+
+/// The limit which an insert would have exceeded
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum LimitError {
+    Vertices { limit: usize },
+    Phantoms { limit: usize },
+    EdgesPerVertex { limit: usize },
+}
+
+impl Limits {
+    /// Check that inserting `key` with `edge_tuples` would stay within these limits
+    pub(crate) fn check<K,V,E,M>(&self, vertex_vec: &VertexVec<K,V,E>, key: &K, edge_tuples: &[(K,M)]) -> Result<(),LimitError>
+        where K: PartialEq {
+        self.check_appended(vertex_vec, key, 0, edge_tuples)
+    }
+    /// As `check`, for `edge_tuples` appended to the `existing` edges of a resident `key` rather than replacing them
+    pub(crate) fn check_appended<K,V,E,M>(&self, vertex_vec: &VertexVec<K,V,E>, key: &K, existing: usize, edge_tuples: &[(K,M)])
+        -> Result<(),LimitError> where K: PartialEq {
+        if let Some(limit) = self.max_edges_per_vertex {
+            if existing + edge_tuples.len() > limit {
+                return Err(LimitError::EdgesPerVertex{ limit });
+            }
+        }
+        if self.max_vertices.is_none() && self.max_phantoms.is_none() {
+            return Ok(());
+        }
+
+        let phantoms = Counts::get(&vertex_vec.counts.phantoms);
+        let vertices = Counts::get(&vertex_vec.counts.resident) + phantoms;
+
+        let mut created: Vec<&K> = Vec::new();
+        for (k, _) in edge_tuples.iter() {
+            if k != key && !created.contains(&k) && Vertex::find(k, vertex_vec).is_none() {
+                created.push(k);
+            }
+        }
+        let (vertices, phantoms) = match Vertex::find(key, vertex_vec) {
+            None => (vertices + created.len() + 1, phantoms + created.len()),
+            Some(vertex) if vertex.is_resident() => (vertices + created.len(), phantoms + created.len()),
+            // Promoted
+            Some(_) => (vertices + created.len(), phantoms + created.len() - 1),
+        };
+
+        match (self.max_vertices, self.max_phantoms) {
+            (Some(limit), _) if vertices > limit => Err(LimitError::Vertices{ limit }),
+            (_, Some(limit)) if phantoms > limit => Err(LimitError::Phantoms{ limit }),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Panic on behalf of an insert which would have exceeded the limits. Callers release the graph lock first, so as not
+/// to poison it.
+pub(crate) fn exceeded(error: LimitError) -> ! {
+    panic!("insert exceeded the graph's limits: {:?}", error);
+}
+
+impl<K,V,E> DependencyGraph<K,V,E> {
+    pub fn with_limits(limits: Limits) -> Self {
+        let mut graph = DependencyGraph::new();
+        graph.limits = limits;
+        graph
+    }
+    /// The limits enforced on inserts through this handle
+    pub fn limits(&self) -> Limits {
+        self.limits
+    }
+    /// Set the limits enforced on inserts through this handle. Vertices already present are unaffected.
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::panic::{self,AssertUnwindSafe};
+    use super::{Limits,LimitError};
+    use super::super::DependencyGraph;
+
+    #[test]
+    fn limits() {
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::with_limits(Limits{
+            max_vertices: Some(5),
+            max_phantoms: Some(2),
+            max_edges_per_vertex: Some(3),
+        });
+
+        assert!(graph.try_insert("A", "Alpha", vec![("X",None),("Y",None)]).is_ok());
+        assert_eq!(graph.try_insert("B", "Bravo", vec![("Z",None)]), Err(LimitError::Phantoms{ limit: 2 }));
+        assert_eq!(graph.try_insert("B", "Bravo", vec![("A",None),("A",None),("A",None),("A",None)]),
+            Err(LimitError::EdgesPerVertex{ limit: 3 }));
+        assert_eq!(graph.iter().count(), 1, "failed inserts leave the graph unchanged");
+
+        // Promoting a phantom frees up room for another
+        assert!(graph.try_insert("X", "X-ray", vec![("Z",None)]).is_ok());
+        assert!(graph.try_insert("B", "Bravo", vec![]).is_ok());
+        assert_eq!(graph.try_insert("C", "Charlie", vec![]), Err(LimitError::Vertices{ limit: 5 }));
+
+        graph.set_limits(Limits::default());
+        graph.insert("C", "Charlie", vec![("W",None)]);
+        assert_eq!(graph.metrics().phantoms, 3);
+    }
+
+    #[test]
+    fn panics_release_lock() {
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::with_limits(Limits{
+            max_phantoms: Some(1),
+            ..Limits::default()
+        });
+        graph.insert("A", 1, vec![("X",None)]);
+        let mut exceeds = |f: &mut dyn FnMut(&mut DependencyGraph<&'static str,i32,()>)| {
+            let failed = panic::catch_unwind(AssertUnwindSafe(|| f(&mut graph)));
+            assert!(failed.unwrap_err().downcast::<String>().unwrap().contains("Phantoms"));
+            // Still usable, and unchanged
+            assert_eq!(graph.edges(&"A"), vec![("X",None)]);
+        };
+
+        exceeds(&mut |graph| drop(graph.insert("B", 2, vec![("Y",None)])));
+        exceeds(&mut |graph| drop(graph.upsert("A", 2, vec![("Y",None)], |v, n| *v += n)));
+        exceeds(&mut |graph| graph.extend(vec![("B", 2, vec![("Y",None)])]));
+        exceeds(&mut |graph| drop(graph.insert_batch(vec![("B", 2, vec![("A",None)]), ("C", 3, vec![("Y",None)])])));
+        assert_eq!(graph.get(&"B"), Some(2), "inserted before the batch failed");
+        assert_eq!(graph.get(&"A"), Some(1));
+    }
+}
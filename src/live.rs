@@ -0,0 +1,199 @@
+//! Iteration which observes changes made to the graph while it's underway.
+//!
+//! `TopoIter` works from a snapshot. A `LiveIter` instead re-reads the graph whenever it has changed since the last
+//! step, so that vertices inserted meanwhile are yielded if they come after what has been yielded already, and
+//! vertices removed before being reached are not yielded at all. Steps over an unchanged graph reuse the last snapshot
+//! and pick up where the previous step left off.
+
+use std::sync::Arc;
+
+use super::{Counts,DependencyGraph,TopoItem,VertexState,VertexVec};
+use adjacency::Adjacency;
+
+/// Returned by `DependencyGraph::iter_live`
+pub struct LiveIter<K,V,E> {
+    graph: DependencyGraph<K,V,E>,
+    /// Keys already yielded, in order
+    yielded: Vec<K>,
+    /// As of the graph's last change seen, if any step has been taken
+    snapshot: Option<Snapshot<K>>,
+}
+
+/// The structure of the graph as of a given count of its changes, and the progress through it
+struct Snapshot<K> {
+    counts: Arc<Counts>,
+    changes: usize,
+    keys: Vec<Option<K>>,
+    adjacency: Adjacency,
+    order: Vec<usize>,
+    /// Position of each slot in `order`
+    rank: Vec<usize>,
+    /// Whether each slot's key has been yielded
+    yielded: Vec<bool>,
+    /// Slots which something yielded depends on, and which depend on something yielded, respectively
+    behind: Vec<bool>,
+    ahead: Vec<bool>,
+    /// Position in `order` before which nothing remains to be yielded
+    cursor: usize,
+}
+
+impl<K,V,E> DependencyGraph<K,V,E> {
+    /// As `iter`, but reflecting inserts and removals made while iterating, by this or any other handle.
+    ///
+    /// A vertex is yielded if resident and unmasked once it's reached, unless it is a dependency of something already
+    /// yielded (and not also a dependent of it, through a cycle). A key is yielded at most once, even if removed and
+    /// inserted again. The graph is only locked within each call to `next`, so may be changed between them.
+    pub fn iter_live(&self) -> LiveIter<K,V,E> {
+        LiveIter{
+            graph: self.clone(),
+            yielded: Vec::new(),
+            snapshot: None,
+        }
+    }
+}
+
+impl<K,V,E> LiveIter<K,V,E> {
+    /// The keys yielded so far, in order
+    pub fn yielded(&self) -> &[K] {
+        &self.yielded
+    }
+}
+
+impl<K> Snapshot<K> where K: PartialEq+Clone {
+    /// Read the graph afresh, carrying over which slots were yielded from `previous` where their keys are unchanged
+    fn new<V,E>(vertex_vec: &VertexVec<K,V,E>, changes: usize, previous: Option<Snapshot<K>>, yielded: &[K]) -> Self {
+        let adjacency = Adjacency::new(vertex_vec);
+        let keys: Vec<Option<K>> = vertex_vec.iter().map(|vertex| vertex.key.lock().unwrap().clone()).collect();
+        let was_yielded = |i: usize, key: &K| match previous {
+            Some(ref previous) if previous.keys.get(i).is_some_and(|k| k.as_ref() == Some(key)) => previous.yielded[i],
+            _ => yielded.contains(key),
+        };
+        let yielded: Vec<bool> = keys.iter().enumerate().map(|(i, key)| key.as_ref().is_some_and(|key| was_yielded(i, key))).collect();
+        let roots: Vec<usize> = (0..keys.len()).filter(|&i| yielded[i]).collect();
+        let order = adjacency.topo_order();
+        let mut rank = vec![usize::MAX; keys.len()];
+        for (r, &i) in order.iter().enumerate() {
+            rank[i] = r;
+        }
+        Snapshot{
+            counts: vertex_vec.counts.clone(),
+            changes,
+            behind: adjacency.reachable(&roots, &adjacency.out),
+            ahead: adjacency.reachable(&roots, &adjacency.inc),
+            keys,
+            adjacency,
+            order,
+            rank,
+            yielded,
+            cursor: 0,
+        }
+    }
+
+    /// Record the slot at `cursor` as yielded
+    fn advance(&mut self) {
+        let i = self.order[self.cursor];
+        self.yielded[i] = true;
+        self.adjacency.reach(&mut self.behind, &[i], &self.adjacency.out, |_| {});
+        // Slots already passed over may now be due, through a cycle
+        let (rank, cursor) = (&self.rank, &mut self.cursor);
+        self.adjacency.reach(&mut self.ahead, &[i], &self.adjacency.inc, |j| *cursor = (*cursor).min(rank[j]));
+    }
+}
+
+impl<K,V,E> Iterator for LiveIter<K,V,E> where K: PartialEq+Clone, V: Clone {
+    type Item = TopoItem<K,V>;
+
+    fn next(&mut self) -> Option<TopoItem<K,V>> {
+        let vertex_vec = self.graph.vertex_vec.lock().unwrap();
+        let changes = Counts::get(&vertex_vec.counts.changes);
+        let current = self.snapshot.as_ref().is_some_and(|s| Arc::ptr_eq(&s.counts, &vertex_vec.counts) && s.changes == changes);
+        if !current {
+            self.snapshot = Some(Snapshot::new(&vertex_vec, changes, self.snapshot.take(), &self.yielded));
+        }
+        let snapshot = self.snapshot.as_mut().expect("just taken");
+
+        while snapshot.cursor < snapshot.order.len() {
+            let i = snapshot.order[snapshot.cursor];
+            let key = match snapshot.keys[i] {
+                Some(ref key) if !snapshot.yielded[i] && (snapshot.ahead[i] || !snapshot.behind[i]) => key.clone(),
+                _ => {
+                    snapshot.cursor += 1;
+                    continue;
+                },
+            };
+            let (value, edges) = match *vertex_vec[i].state.lock().unwrap() {
+                VertexState::Resident{ ref value, ref edges } => {
+                    (value.clone(), edges.iter().filter_map(|e| e.dest_vertex.key.lock().unwrap().clone()).collect())
+                },
+                VertexState::Phantom => {
+                    snapshot.cursor += 1;
+                    continue;
+                },
+            };
+            snapshot.advance();
+            self.yielded.push(key.clone());
+            return Some(TopoItem{ key, value, edges, status: vertex_vec[i].status(), group: None });
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::DependencyGraph;
+
+    #[test]
+    fn iter_live() {
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+        graph.insert("A", "Alpha", vec![]);
+        graph.insert("B", "Bravo", vec![("A",None)]);
+        graph.insert("D", "Delta", vec![("B",None)]);
+
+        let mut iter = graph.iter_live();
+        assert_eq!(iter.next().map(|i| i.key), Some("A"));
+
+        // After the current position, before it, and not yet reached
+        graph.insert("C", "Charlie", vec![("A",None)]);
+        graph.insert("Z", "Zulu", vec![]);
+        graph.insert("A", "Alpha", vec![("Y",None)]);
+        graph.insert("Y", "Yankee", vec![]);
+        graph.remove("D");
+
+        let mut rest: Vec<_> = iter.by_ref().map(|i| i.key).collect();
+        rest.sort();
+        assert_eq!(rest, vec!["B","C","Z"]);
+        assert_eq!(iter.yielded().len(), 4);
+    }
+
+    #[test]
+    fn iter_live_cycle() {
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+        graph.insert("A", "Alpha", vec![("B",None)]);
+        graph.insert("B", "Bravo", vec![("A",None)]);
+
+        let mut keys: Vec<_> = graph.iter_live().map(|i| i.key).collect();
+        keys.sort();
+        assert_eq!(keys, vec!["A","B"]);
+    }
+
+    #[test]
+    fn iter_live_unchanged() {
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+        graph.insert("A", "Alpha", vec![]);
+        graph.insert("B", "Bravo", vec![("A",None)]);
+        graph.insert("C", "Charlie", vec![("B",None)]);
+
+        let mut iter = graph.iter_live();
+        assert_eq!(iter.next().map(|i| i.key), Some("A"));
+        let order = iter.snapshot.as_ref().unwrap().order.as_ptr();
+
+        // Values are read as each vertex is yielded, without re-reading the structure
+        graph.with_value_mut(&"B", |v| *v = "Bravo Prime");
+        assert_eq!(iter.next().map(|i| i.value), Some("Bravo Prime"));
+        assert_eq!(iter.snapshot.as_ref().unwrap().order.as_ptr(), order);
+
+        // Masking changes the structure
+        graph.mask(&"C");
+        assert!(iter.next().is_none());
+    }
+}
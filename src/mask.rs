@@ -0,0 +1,124 @@
+//! Masking of vertices, which are then passed over by iteration and evaluation without being removed.
+//!
+//! A masked vertex contributes nothing to its dependents, much as if it were a phantom, until unmasked.
+
+use std::borrow::Borrow;
+
+use super::{DependencyGraph,Vertex,VertexState};
+use adjacency::Adjacency;
+
+impl<K,V,E> DependencyGraph<K,V,E> {
+    /// Mask a resident vertex. Returns false if the key is not resident.
+    pub fn mask<Q>(&self, key: &Q) -> bool where K: Borrow<Q>, Q: ?Sized+PartialEq {
+        self.set_masked(key, true)
+    }
+
+    /// Unmask a resident vertex. Returns false if the key is not resident.
+    pub fn unmask<Q>(&self, key: &Q) -> bool where K: Borrow<Q>, Q: ?Sized+PartialEq {
+        self.set_masked(key, false)
+    }
+
+    /// Whether the vertex for a key is resident and masked
+    pub fn is_masked<Q>(&self, key: &Q) -> bool where K: Borrow<Q>, Q: ?Sized+PartialEq {
+        let vertex_vec = self.vertex_vec.lock().unwrap();
+        match Vertex::find(key, &vertex_vec) {
+            Some(vertex) => vertex.is_resident() && *vertex.masked.lock().unwrap(),
+            None => false,
+        }
+    }
+
+    /// Mask a resident vertex along with everything which transitively depends on it, returning the keys masked.
+    /// Dependents inserted later are not masked. Empty if the key is not resident.
+    pub fn mask_with_dependents(&self, key: &K) -> Vec<K> where K: PartialEq+Clone {
+        let vertex_vec = self.vertex_vec.lock().unwrap();
+        let start = match vertex_vec.iter().position(|v| v.key.lock().unwrap().as_ref() == Some(key)) {
+            Some(start) => start,
+            None => return Vec::new(),
+        };
+        let adjacency = Adjacency::new(&vertex_vec);
+        if !adjacency.resident[start] {
+            return Vec::new();
+        }
+
+        let mut masked = Vec::new();
+        let mut seen = vec![false; vertex_vec.len()];
+        let mut stack = vec![start];
+        seen[start] = true;
+        while let Some(i) = stack.pop() {
+            *vertex_vec[i].masked.lock().unwrap() = true;
+            vertex_vec[i].counts.changed();
+            masked.extend(vertex_vec[i].key.lock().unwrap().clone());
+            for &j in adjacency.inc[i].iter() {
+                if !seen[j] {
+                    seen[j] = true;
+                    stack.push(j);
+                }
+            }
+        }
+        masked
+    }
+
+    fn set_masked<Q>(&self, key: &Q, masked: bool) -> bool where K: Borrow<Q>, Q: ?Sized+PartialEq {
+        let vertex_vec = self.vertex_vec.lock().unwrap();
+        match Vertex::find(key, &vertex_vec) {
+            Some(vertex) => {
+                if let VertexState::Phantom = *vertex.state.lock().unwrap() {
+                    return false;
+                }
+                *vertex.masked.lock().unwrap() = masked;
+                vertex.counts.changed();
+                true
+            },
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::DependencyGraph;
+
+    #[test]
+    fn mask() {
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+        graph.insert("A", "Alpha",   vec![]);
+        graph.insert("B", "Bravo",   vec![("A",None)]);
+        graph.insert("C", "Charlie", vec![("B",None),("Z",None)]);
+        graph.insert("D", "Delta",   vec![]);
+
+        assert!(graph.mask(&"B"));
+        assert!(!graph.mask(&"Z"));
+        assert!(graph.is_masked(&"B"));
+        let keys: Vec<_> = graph.iter().map(|i| i.key).collect();
+        assert_eq!(keys.len(), 3);
+        assert!(!keys.contains(&"B"));
+
+        assert!(graph.unmask(&"B"));
+        assert_eq!(graph.iter().count(), 4);
+
+        let mut masked = graph.mask_with_dependents(&"A");
+        masked.sort();
+        assert_eq!(masked, vec!["A","B","C"]);
+        let keys: Vec<_> = graph.iter().map(|i| i.key).collect();
+        assert_eq!(keys, vec!["D"]);
+        assert_eq!(graph.layout().vertices.len(), 5, "masked vertices are still laid out");
+    }
+
+    #[test]
+    fn reinsert_after_remove() {
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+        graph.insert("A", "Alpha",   vec![]);
+        graph.insert("B", "Bravo",   vec![("A",None)]);
+        assert!(graph.mask(&"A"));
+        assert!(graph.mark_dirty(&"A"));
+
+        // B's edge keeps the vertex for A as a phantom, to be reused when A is inserted again
+        graph.remove("A");
+        graph.insert("A", "Alpha again", vec![]);
+
+        assert!(!graph.is_masked(&"A"), "masking should not outlive removal");
+        assert!(graph.dirty_closure().is_empty(), "nor should a dirty mark");
+        let keys: Vec<_> = graph.iter().map(|i| i.key).collect();
+        assert_eq!(keys, vec!["A","B"]);
+    }
+}
@@ -0,0 +1,219 @@
+//! Structural health metrics and path lengths, for tracking how a graph's shape changes over time.
+
+use std::collections::{HashMap,VecDeque};
+use std::hash::Hash;
+
+use super::DependencyGraph;
+use adjacency::{Adjacency,strongly_connected};
+
+/// Beyond this many resident vertices, shortest paths are measured from an evenly spaced sample of sources
+const PATH_SAMPLE: usize = 256;
+
+/// Computed by `DependencyGraph::metrics`. Only resident vertices, and the edges between them, are measured.
+#[derive(Clone,Debug,PartialEq)]
+pub struct Metrics {
+    pub vertices: usize,
+    pub phantoms: usize,
+    /// Distinct dependencies between resident vertices, excluding self-edges
+    pub edges: usize,
+    /// `edges` as a proportion of the `vertices * (vertices - 1)` possible
+    pub density: f64,
+    /// Mean shortest path length, in hops along dependency edges, over every pair connected by a path
+    pub average_path_length: f64,
+    /// Greatest shortest path length between any connected pair
+    pub longest_path_length: usize,
+    /// Whether the path lengths were measured from a sample of sources, rather than all of them
+    pub sampled: bool,
+    /// Weakly connected components
+    pub components: usize,
+    /// Strongly connected components containing a cycle
+    pub cycles: usize,
+}
+
+impl<K,V,E> DependencyGraph<K,V,E> {
+    /// Measure the graph as of now
+    pub fn metrics(&self) -> Metrics {
+        let vertex_vec = self.vertex_vec.lock().unwrap();
+        let adjacency = Adjacency::new(&vertex_vec);
+        let resident: Vec<usize> = (0..vertex_vec.len()).filter(|&i| adjacency.resident[i]).collect();
+        let phantoms = (0..vertex_vec.len())
+            .filter(|&i| !adjacency.resident[i] && vertex_vec[i].key.lock().unwrap().is_some())
+            .count();
+
+        let deps = resident_deps(&adjacency);
+        let edges: usize = deps.iter().map(|d| d.len()).sum();
+        let n = resident.len();
+        let density = if n > 1 { edges as f64 / (n * (n - 1)) as f64 } else { 0.0 };
+
+        let (sampled, paths) = path_lengths(&deps, &resident);
+        let (total, pairs, longest) = paths;
+
+        // Weak components, by flood fill in both directions
+        let mut components = 0;
+        let mut seen = vec![false; vertex_vec.len()];
+        let mut stack = Vec::new();
+        for &root in resident.iter() {
+            if seen[root] {
+                continue;
+            }
+            components += 1;
+            seen[root] = true;
+            stack.push(root);
+            while let Some(i) = stack.pop() {
+                for &j in adjacency.out[i].iter().chain(adjacency.inc[i].iter()) {
+                    if adjacency.resident[j] && !seen[j] {
+                        seen[j] = true;
+                        stack.push(j);
+                    }
+                }
+            }
+        }
+
+        let cycles = strongly_connected(&deps).into_iter()
+            .filter(|c| adjacency.resident[c[0]] && (c.len() > 1 || adjacency.out[c[0]].contains(&c[0])))
+            .count();
+
+        Metrics{
+            vertices: n,
+            phantoms,
+            edges,
+            density,
+            average_path_length: if pairs > 0 { total as f64 / pairs as f64 } else { 0.0 },
+            longest_path_length: longest,
+            sampled,
+            components,
+            cycles,
+        }
+    }
+
+    /// Hop counts along dependency edges from `from` to every vertex it reaches, phantoms included, and to itself (zero).
+    /// Empty if `from` is absent.
+    pub fn distance_map(&self, from: &K) -> HashMap<K,usize> where K: Eq+Hash+Clone {
+        let vertex_vec = self.vertex_vec.lock().unwrap();
+        let source = match vertex_vec.iter().position(|v| v.key.lock().unwrap().as_ref() == Some(from)) {
+            Some(source) => source,
+            None => return HashMap::new(),
+        };
+        let adjacency = Adjacency::new(&vertex_vec);
+        let deps: Vec<Vec<usize>> = (0..vertex_vec.len()).map(|i| adjacency.dependencies(i)).collect();
+
+        let mut distance = vec![None; vertex_vec.len()];
+        let reached = breadth_first(&deps, source, &mut distance);
+        std::iter::once((source, 0)).chain(reached)
+            .filter_map(|(i, hops)| Some((vertex_vec[i].key.lock().unwrap().clone()?, hops)))
+            .collect()
+    }
+
+    /// The greatest number of hops from `key` to anything it depends on, transitively. None if `key` is absent.
+    pub fn eccentricity(&self, key: &K) -> Option<usize> where K: Eq+Hash+Clone {
+        self.distance_map(key).values().max().cloned()
+    }
+
+    /// The greatest eccentricity of any resident vertex, considering resident dependencies only.
+    /// Exact up to `PATH_SAMPLE` (256) resident vertices; beyond that a lower bound, measured from a sample of them.
+    pub fn diameter(&self) -> usize {
+        let vertex_vec = self.vertex_vec.lock().unwrap();
+        let adjacency = Adjacency::new(&vertex_vec);
+        let resident: Vec<usize> = (0..vertex_vec.len()).filter(|&i| adjacency.resident[i]).collect();
+        (path_lengths(&resident_deps(&adjacency), &resident).1).2
+    }
+}
+
+/// Resident-to-resident dependencies of each slot
+fn resident_deps(adjacency: &Adjacency) -> Vec<Vec<usize>> {
+    (0..adjacency.out.len()).map(|i| {
+        adjacency.dependencies(i).into_iter().filter(|&j| adjacency.resident[j]).collect()
+    }).collect()
+}
+
+/// Total, count, and maximum of the shortest path lengths from `resident` (or a sample of them) to everything reachable
+fn path_lengths(deps: &[Vec<usize>], resident: &[usize]) -> (bool,(usize,usize,usize)) {
+    let n = resident.len();
+    let sampled = n > PATH_SAMPLE;
+    let sources: Vec<usize> = if sampled {
+        (0..PATH_SAMPLE).map(|s| resident[s * n / PATH_SAMPLE]).collect()
+    }else{
+        resident.to_vec()
+    };
+
+    let (mut total, mut pairs, mut longest) = (0, 0, 0);
+    let mut distance = vec![None; deps.len()];
+    for source in sources {
+        for d in distance.iter_mut() {
+            *d = None;
+        }
+        for (_, hops) in breadth_first(deps, source, &mut distance) {
+            total += hops;
+            pairs += 1;
+            longest = longest.max(hops);
+        }
+    }
+    (sampled, (total, pairs, longest))
+}
+
+/// Slots reachable from `source` with their hop counts, excluding `source` itself. `distance` must be all None.
+fn breadth_first(deps: &[Vec<usize>], source: usize, distance: &mut [Option<usize>]) -> Vec<(usize,usize)> {
+    let mut reached = Vec::new();
+    let mut queue = VecDeque::new();
+    distance[source] = Some(0);
+    queue.push_back(source);
+    while let Some(i) = queue.pop_front() {
+        let next = distance[i].unwrap() + 1;
+        for &j in deps[i].iter() {
+            if distance[j].is_none() {
+                distance[j] = Some(next);
+                reached.push((j, next));
+                queue.push_back(j);
+            }
+        }
+    }
+    reached
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::DependencyGraph;
+
+    #[test]
+    fn metrics() {
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+        graph.insert("A", "Alpha",   vec![]);
+        graph.insert("B", "Bravo",   vec![("A",None),("Z",None)]);
+        graph.insert("C", "Charlie", vec![("B",None)]);
+        graph.insert("D", "Delta",   vec![("E",None)]);
+        graph.insert("E", "Echo",    vec![("D",None),("E",None)]);
+
+        let metrics = graph.metrics();
+        assert_eq!(metrics.vertices, 5);
+        assert_eq!(metrics.phantoms, 1);
+        assert_eq!(metrics.edges, 4);
+        assert_eq!(metrics.density, 4.0 / 20.0);
+        // B-A, C-B, C-A, D-E, E-D
+        assert_eq!(metrics.average_path_length, 6.0 / 5.0);
+        assert_eq!(metrics.longest_path_length, 2);
+        assert!(!metrics.sampled);
+        assert_eq!(metrics.components, 2);
+        assert_eq!(metrics.cycles, 1);
+    }
+
+    #[test]
+    fn distances() {
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+        graph.insert("A", "Alpha",   vec![]);
+        graph.insert("B", "Bravo",   vec![("A",None)]);
+        graph.insert("C", "Charlie", vec![("B",None),("A",None)]);
+        graph.insert("D", "Delta",   vec![("C",None),("Z",None)]);
+
+        let distances = graph.distance_map(&"D");
+        assert_eq!(distances.len(), 5);
+        assert_eq!(distances["D"], 0);
+        assert_eq!(distances["Z"], 1);
+        assert_eq!(distances["A"], 2, "via C, rather than B");
+        assert!(graph.distance_map(&"X").is_empty());
+
+        assert_eq!(graph.eccentricity(&"D"), Some(2));
+        assert_eq!(graph.eccentricity(&"A"), Some(0));
+        assert_eq!(graph.eccentricity(&"X"), None);
+        assert_eq!(graph.diameter(), 2);
+    }
+}
@@ -0,0 +1,185 @@
+//! Queries on the immediate neighbors of a vertex.
+
+use std::borrow::Borrow;
+use std::sync::Arc;
+
+use super::{DependencyGraph,Vertex,VertexState,VertexStatus};
+
+impl<K,V,E> DependencyGraph<K,V,E> {
+    /// The distinct keys a resident vertex has edges to, in edge order, each with its status. Phantoms are included,
+    /// flagged as such, and so may be filtered out by the caller. Empty if the key is not resident.
+    pub fn out_neighbors<Q>(&self, key: &Q) -> std::vec::IntoIter<(K,VertexStatus)> where K: Borrow<Q>+PartialEq+Clone, Q: ?Sized+PartialEq {
+        let vertex_vec = self.vertex_vec.lock().unwrap();
+        let mut neighbors: Vec<(K,VertexStatus)> = Vec::new();
+        if let Some(vertex) = Vertex::find(key, &vertex_vec) {
+            // Collected first, as a self-edge's status can't be read while the state lock is held
+            let dests: Vec<_> = match *vertex.state.lock().unwrap() {
+                VertexState::Resident{ ref edges, .. } => edges.iter().map(|e| e.dest_vertex.clone()).collect(),
+                VertexState::Phantom => Vec::new(),
+            };
+            for dest in dests {
+                if let Some(k) = dest.key.lock().unwrap().clone() {
+                    if !neighbors.iter().any(|n| n.0 == k) {
+                        neighbors.push((k, dest.status()));
+                    }
+                }
+            }
+        }
+        neighbors.into_iter()
+    }
+
+    /// The resident vertices with edges to `key`, in no particular order. No reverse index is kept, so each call scans
+    /// the edges of every vertex in the graph.
+    pub fn dependents<Q>(&self, key: &Q) -> std::vec::IntoIter<K> where K: Borrow<Q>+Clone, Q: ?Sized+PartialEq {
+        let vertex_vec = self.vertex_vec.lock().unwrap();
+        let target = match Vertex::find(key, &vertex_vec) {
+            Some(target) => target.clone(),
+            None => return Vec::new().into_iter(),
+        };
+        let dependents: Vec<K> = vertex_vec.iter().filter(|vertex| {
+            match *vertex.state.lock().unwrap() {
+                VertexState::Resident{ ref edges, .. } => edges.iter().any(|e| Arc::ptr_eq(&e.dest_vertex, &target)),
+                VertexState::Phantom => false,
+            }
+        }).filter_map(|vertex| vertex.key.lock().unwrap().clone()).collect();
+        dependents.into_iter()
+    }
+
+    /// Whether the resident vertex `from` has an edge to `to`, which may be a phantom
+    pub fn contains_edge<Q>(&self, from: &Q, to: &Q) -> bool where K: Borrow<Q>, Q: ?Sized+PartialEq {
+        let vertex_vec = self.vertex_vec.lock().unwrap();
+        let vertex = match Vertex::find(from, &vertex_vec) {
+            Some(vertex) => vertex,
+            None => return false,
+        };
+        let state = vertex.state.lock().unwrap();
+        match *state {
+            VertexState::Resident{ ref edges, .. } => edges.iter().any(|e| e.dest_vertex.key.lock().unwrap().as_ref().map(Borrow::borrow) == Some(to)),
+            VertexState::Phantom => false,
+        }
+    }
+
+    /// The number of edges to `key`, counting duplicates and self-edges. This is read from the vertex's refcount, and so
+    /// doesn't require a scan.
+    pub fn in_degree<Q>(&self, key: &Q) -> usize where K: Borrow<Q>, Q: ?Sized+PartialEq {
+        let vertex_vec = self.vertex_vec.lock().unwrap();
+        match Vertex::find(key, &vertex_vec) {
+            // One count is held for residency, and one by each incoming edge
+            Some(vertex) => *vertex.refcount.lock().unwrap() - vertex.is_resident() as usize,
+            None => 0,
+        }
+    }
+
+    /// The number of edges from `key`, counting duplicates. Zero if the key is not resident.
+    pub fn out_degree<Q>(&self, key: &Q) -> usize where K: Borrow<Q>, Q: ?Sized+PartialEq {
+        let vertex_vec = self.vertex_vec.lock().unwrap();
+        let vertex = match Vertex::find(key, &vertex_vec) {
+            Some(vertex) => vertex,
+            None => return 0,
+        };
+        let state = vertex.state.lock().unwrap();
+        match *state {
+            VertexState::Resident{ ref edges, .. } => edges.len(),
+            VertexState::Phantom => 0,
+        }
+    }
+
+    /// The resident vertices which nothing has an edge to, such as the top-level targets of a build, in no particular order
+    pub fn roots(&self) -> std::vec::IntoIter<K> where K: Clone {
+        let vertex_vec = self.vertex_vec.lock().unwrap();
+        let roots: Vec<K> = vertex_vec.iter()
+            // A refcount of one is held by residency alone
+            .filter(|vertex| vertex.is_resident() && *vertex.refcount.lock().unwrap() == 1)
+            .filter_map(|vertex| vertex.key.lock().unwrap().clone())
+            .collect();
+        roots.into_iter()
+    }
+
+    /// The resident vertices with no edges of their own, in no particular order. An edge to a phantom counts, as
+    /// the phantom may yet be inserted.
+    pub fn leaves(&self) -> std::vec::IntoIter<K> where K: Clone {
+        let vertex_vec = self.vertex_vec.lock().unwrap();
+        let leaves: Vec<K> = vertex_vec.iter().filter(|vertex| {
+            match *vertex.state.lock().unwrap() {
+                VertexState::Resident{ ref edges, .. } => edges.is_empty(),
+                VertexState::Phantom => false,
+            }
+        }).filter_map(|vertex| vertex.key.lock().unwrap().clone()).collect();
+        leaves.into_iter()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::{DependencyGraph,VertexStatus};
+
+    #[test]
+    fn out_neighbors() {
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+        graph.insert("A", "Alpha", vec![]);
+        graph.insert("B", "Bravo", vec![("Z",None),("A",None),("Z",None),("B",None)]);
+
+        let neighbors: Vec<_> = graph.out_neighbors(&"B").collect();
+        assert_eq!(neighbors, vec![("Z",VertexStatus::Phantom),("A",VertexStatus::Resident),("B",VertexStatus::Resident)]);
+        let resident: Vec<_> = graph.out_neighbors(&"B").filter(|n| n.1 != VertexStatus::Phantom).map(|n| n.0).collect();
+        assert_eq!(resident, vec!["A","B"]);
+        assert_eq!(graph.out_neighbors(&"Z").count(), 0);
+    }
+
+    #[test]
+    fn dependents() {
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+        graph.insert("A", "Alpha",   vec![("Z",None),("A",None)]);
+        graph.insert("B", "Bravo",   vec![("Z",None),("Z",None)]);
+        graph.insert("C", "Charlie", vec![("A",None)]);
+
+        let mut dependents: Vec<_> = graph.dependents(&"Z").collect();
+        dependents.sort();
+        assert_eq!(dependents, vec!["A","B"]);
+        let mut dependents: Vec<_> = graph.dependents(&"A").collect();
+        dependents.sort();
+        assert_eq!(dependents, vec!["A","C"]);
+        assert_eq!(graph.dependents(&"C").count(), 0);
+        assert_eq!(graph.dependents(&"Y").count(), 0);
+    }
+
+    #[test]
+    fn contains_edge() {
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+        graph.insert("A", "Alpha", vec![("Z",None),("A",None)]);
+
+        assert!(graph.contains_edge(&"A", &"Z"));
+        assert!(graph.contains_edge(&"A", &"A"));
+        assert!(!graph.contains_edge(&"Z", &"A"));
+        assert!(!graph.contains_edge(&"A", &"Y"));
+    }
+
+    #[test]
+    fn degree() {
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+        graph.insert("A", "Alpha", vec![("Z",None),("A",None)]);
+        graph.insert("B", "Bravo", vec![("Z",None),("Z",None),("A",None)]);
+
+        assert_eq!((graph.in_degree(&"Z"), graph.out_degree(&"Z")), (3, 0));
+        assert_eq!((graph.in_degree(&"A"), graph.out_degree(&"A")), (2, 2));
+        assert_eq!((graph.in_degree(&"B"), graph.out_degree(&"B")), (0, 3));
+        assert_eq!((graph.in_degree(&"Y"), graph.out_degree(&"Y")), (0, 0));
+
+        graph.remove("A");
+        assert_eq!(graph.in_degree(&"A"), 1, "a phantom, held by B's edge");
+    }
+
+    #[test]
+    fn roots_and_leaves() {
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+        graph.insert("A", "Alpha",   vec![]);
+        graph.insert("B", "Bravo",   vec![("A",None),("Z",None)]);
+        graph.insert("C", "Charlie", vec![("A",None)]);
+        graph.insert("D", "Delta",   vec![("D",None)]);
+
+        let mut roots: Vec<_> = graph.roots().collect();
+        roots.sort();
+        assert_eq!(roots, vec!["B","C"]);
+        assert_eq!(graph.leaves().collect::<Vec<_>>(), vec!["A"]);
+    }
+}
@@ -0,0 +1,125 @@
+//! Pinning of vertices, exempting them from automatic removal.
+//!
+//! A pinned vertex is retained by `gc` as though it were a root, along with everything it depends on. Its edges are
+//! kept by `expire_edges`, and `retain` neither cascades to it nor drops its edges. Limits only ever reject inserts,
+//! so they leave it be too. Explicit removal, by `remove`, `take`, `retain`'s own predicate, and the like, still
+//! applies. Pins are discarded when a vertex is removed.
+
+use std::borrow::Borrow;
+
+use super::{DependencyGraph,Vertex};
+
+impl<K,V,E> DependencyGraph<K,V,E> {
+    /// Pin a resident vertex. Returns false if the key is not resident.
+    pub fn pin<Q>(&self, key: &Q) -> bool where K: Borrow<Q>, Q: ?Sized+PartialEq {
+        self.set_pinned(key, true)
+    }
+
+    /// Unpin a resident vertex. Returns false if the key is not resident.
+    pub fn unpin<Q>(&self, key: &Q) -> bool where K: Borrow<Q>, Q: ?Sized+PartialEq {
+        self.set_pinned(key, false)
+    }
+
+    /// Whether the vertex for a key is resident and pinned
+    pub fn is_pinned<Q>(&self, key: &Q) -> bool where K: Borrow<Q>, Q: ?Sized+PartialEq {
+        let vertex_vec = self.vertex_vec.lock().unwrap();
+        match Vertex::find(key, &vertex_vec) {
+            Some(vertex) => vertex.is_resident() && *vertex.pinned.lock().unwrap(),
+            None => false,
+        }
+    }
+
+    fn set_pinned<Q>(&self, key: &Q, pinned: bool) -> bool where K: Borrow<Q>, Q: ?Sized+PartialEq {
+        let vertex_vec = self.vertex_vec.lock().unwrap();
+        match Vertex::find(key, &vertex_vec) {
+            Some(vertex) if vertex.is_resident() => {
+                *vertex.pinned.lock().unwrap() = pinned;
+                true
+            },
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::{DependencyGraph,Limits,RemovalPolicy,VertexStatus};
+
+    #[test]
+    fn pin() {
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+        graph.insert("A", "Alpha",   vec![]);
+        graph.insert("B", "Bravo",   vec![("A",None)]);
+        graph.insert("C", "Charlie", vec![]);
+        graph.insert("D", "Delta",   vec![("Z",None)]);
+
+        assert!(graph.pin(&"B"));
+        assert!(!graph.pin(&"Z"), "phantoms can't be pinned");
+        let collected: Vec<_> = graph.gc(&["C"]).into_iter().map(|(k, _)| k).collect();
+        assert_eq!(collected, vec!["D"]);
+        assert!(graph.contains_resident(&"A"), "required by B");
+
+        assert!(graph.unpin(&"B"));
+        assert_eq!(graph.gc(&["C"]).len(), 2);
+
+        // Removal discards the pin
+        graph.insert("E", "Echo", vec![]);
+        graph.pin(&"E");
+        graph.remove("E");
+        graph.insert("E", "Echo", vec![]);
+        assert!(!graph.is_pinned(&"E"));
+    }
+
+    #[test]
+    fn pin_sweeps() {
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+        graph.insert("A", "Alpha", vec![]);
+        graph.insert_expiring("B", "Bravo", vec![("A",None)], 100);
+        graph.insert_expiring("C", "Charlie", vec![("Z",None)], 100);
+        graph.pin(&"C");
+
+        // C's edge outlives its expiry, and so does its phantom
+        assert_eq!(graph.expire_edges(100), vec![("B","A")]);
+        assert_eq!(graph.edges(&"C"), vec![("Z",None)]);
+        graph.unpin(&"C");
+        assert_eq!(graph.expire_edges(100), vec![("C","Z")]);
+        assert_eq!(graph.status(&"Z"), VertexStatus::Missing);
+    }
+
+    #[test]
+    fn pin_retain() {
+        for &policy in [RemovalPolicy::DropEdges, RemovalPolicy::Cascade].iter() {
+            let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+            graph.set_removal_policy(policy);
+            graph.insert("A", 1, vec![]);
+            graph.insert("B", 2, vec![("A",None)]);
+            graph.insert("C", 3, vec![("A",None)]);
+            graph.pin(&"B");
+
+            graph.retain(|k, _| *k != "A");
+            assert!(graph.contains_resident(&"B"), "{:?} spares pinned dependents", policy);
+            assert_eq!(graph.edges(&"B"), vec![("A",None)], "{:?} leaves pinned edges in place", policy);
+            assert_eq!(graph.status(&"A"), VertexStatus::Removed);
+            assert_eq!(graph.contains_resident(&"C"), policy == RemovalPolicy::DropEdges);
+        }
+    }
+
+    #[test]
+    fn pin_limits() {
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::with_limits(Limits{
+            max_vertices: Some(2),
+            ..Limits::default()
+        });
+        graph.insert("A", "Alpha", vec![]);
+        graph.pin(&"A");
+
+        // Rejected rather than making room, so the pinned vertex is untouched
+        assert!(graph.try_insert("A", "Alpha Prime", vec![("Y",None),("Z",None)]).is_err());
+        assert_eq!(graph.get(&"A"), Some("Alpha"));
+        assert!(graph.is_pinned(&"A"));
+        assert!(graph.try_insert("B", "Bravo", vec![("A",None)]).is_ok());
+        assert!(graph.try_insert("C", "Charlie", vec![]).is_err());
+        assert_eq!(graph.len(), 2);
+        assert!(graph.is_pinned(&"A"));
+    }
+}
@@ -0,0 +1,135 @@
+//! Named, typed annotations on resident vertices, kept apart from their values.
+//!
+//! Properties are for tooling (labels, colours, ownership) so that `V` can remain a pure domain payload.
+//! They survive re-insertion of a vertex, and are discarded when it is removed.
+
+use std::borrow::Borrow;
+use std::fmt;
+
+use super::{DependencyGraph,Vertex,VertexState};
+
+#[derive(Clone,Debug,PartialEq)]
+pub enum Property {
+    Str(String),
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+}
+
+/// Renders as a DOT attribute value: strings quoted and escaped, other values bare
+impl fmt::Display for Property {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Property::Str(ref value) => write!(f, "{:?}", value),
+            Property::Int(value) => write!(f, "{}", value),
+            Property::Float(value) => write!(f, "{}", value),
+            Property::Bool(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+impl From<&str> for Property {
+    fn from(value: &str) -> Self {
+        Property::Str(value.to_string())
+    }
+}
+impl From<String> for Property {
+    fn from(value: String) -> Self {
+        Property::Str(value)
+    }
+}
+impl From<i64> for Property {
+    fn from(value: i64) -> Self {
+        Property::Int(value)
+    }
+}
+impl From<f64> for Property {
+    fn from(value: f64) -> Self {
+        Property::Float(value)
+    }
+}
+impl From<bool> for Property {
+    fn from(value: bool) -> Self {
+        Property::Bool(value)
+    }
+}
+
+impl<K,V,E> DependencyGraph<K,V,E> {
+    /// Set a property on a resident vertex, replacing any of the same name. Returns false if the key is not resident.
+    pub fn set_property<P,Q>(&self, key: &Q, name: &str, value: P) -> bool where K: Borrow<Q>, Q: ?Sized+PartialEq, P: Into<Property> {
+        self.with_properties(key, |properties| {
+            let value = value.into();
+            match properties.iter_mut().find(|p| p.0 == name) {
+                Some(property) => property.1 = value,
+                None => properties.push((name.to_string(), value)),
+            }
+        }).is_some()
+    }
+
+    /// The named property of a resident vertex, if set
+    pub fn property<Q>(&self, key: &Q, name: &str) -> Option<Property> where K: Borrow<Q>, Q: ?Sized+PartialEq {
+        self.with_properties(key, |properties| {
+            properties.iter().find(|p| p.0 == name).map(|p| p.1.clone())
+        }).flatten()
+    }
+
+    /// Remove the named property of a resident vertex, returning it if it was set
+    pub fn remove_property<Q>(&self, key: &Q, name: &str) -> Option<Property> where K: Borrow<Q>, Q: ?Sized+PartialEq {
+        self.with_properties(key, |properties| {
+            let i = properties.iter().position(|p| p.0 == name)?;
+            Some(properties.remove(i).1)
+        }).flatten()
+    }
+
+    /// Every property of a resident vertex, in the order first set
+    pub fn properties<Q>(&self, key: &Q) -> Vec<(String,Property)> where K: Borrow<Q>, Q: ?Sized+PartialEq {
+        self.with_properties(key, |properties| properties.clone()).unwrap_or_default()
+    }
+
+    /// Keys of the resident vertices whose named property satisfies `filter`
+    pub fn keys_with_property<F>(&self, name: &str, mut filter: F) -> Vec<K> where K: Clone, F: FnMut(&Property) -> bool {
+        let vertex_vec = self.vertex_vec.lock().unwrap();
+        vertex_vec.iter().filter(|vertex| {
+            vertex.is_resident() && vertex.properties.lock().unwrap().iter().any(|p| p.0 == name && filter(&p.1))
+        }).filter_map(|vertex| vertex.key.lock().unwrap().clone()).collect()
+    }
+
+    fn with_properties<R,F,Q>(&self, key: &Q, f: F) -> Option<R> where K: Borrow<Q>, Q: ?Sized+PartialEq, F: FnOnce(&mut Vec<(String,Property)>) -> R {
+        let vertex_vec = self.vertex_vec.lock().unwrap();
+        let vertex = Vertex::find(key, &vertex_vec)?;
+        if let VertexState::Phantom = *vertex.state.lock().unwrap() {
+            return None;
+        }
+        let mut properties = vertex.properties.lock().unwrap();
+        Some(f(&mut properties))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Property;
+    use super::super::DependencyGraph;
+
+    #[test]
+    fn properties() {
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+        graph.insert("A", "Alpha", vec![("Z",None)]);
+        graph.insert("B", "Bravo", vec![]);
+
+        assert!(graph.set_property(&"A", "color", "red"));
+        assert!(graph.set_property(&"A", "weight", 3));
+        assert!(graph.set_property(&"B", "color", "blue"));
+        assert!(!graph.set_property(&"Z", "color", "grey"), "phantoms can't be annotated");
+        assert!(graph.set_property(&"A", "weight", 4));
+
+        assert_eq!(graph.property(&"A", "weight"), Some(Property::Int(4)));
+        assert_eq!(graph.properties(&"A").len(), 2);
+        assert_eq!(graph.keys_with_property("color", |p| *p == Property::from("blue")), vec!["B"]);
+
+        // Re-inserting retains properties
+        graph.insert("A", "Alpha", vec![]);
+        assert_eq!(graph.remove_property(&"A", "color"), Some(Property::Str("red".to_string())));
+        assert_eq!(graph.property(&"A", "color"), None);
+        assert_eq!(graph.properties(&"A"), vec![("weight".to_string(), Property::Int(4))]);
+    }
+}
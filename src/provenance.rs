@@ -0,0 +1,108 @@
+//! Attribution of edges to the insert calls which created them.
+
+use std::borrow::Borrow;
+
+use super::{DependencyGraph,EdgeMeta,InsertOutcome,LimitError,Vertex,VertexState};
+
+/// Identifies the producer of an edge, as supplied to `insert_with_provenance`
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub enum Provenance {
+    Label(String),
+    Sequence(u64),
+}
+
+impl<K,V,E> DependencyGraph<K,V,E> {
+    /// As `insert`, additionally recording `provenance` on each of the edges created.
+    pub fn insert_with_provenance(&mut self, key: K, value: V, edge_tuples: Vec<(K,Option<E>)>, provenance: Provenance)
+        -> InsertOutcome<K,V,E> where K: PartialEq+Clone {
+        let meta = EdgeMeta{ provenance: Some(provenance), ..EdgeMeta::default() };
+        self.insert_attributed(key, value, edge_tuples, Some(&meta), InsertOutcome::keyed)
+    }
+    /// As `insert_with_provenance`, but failing, with the graph unchanged, if the insert would exceed this handle's `Limits`
+    pub fn try_insert_with_provenance(&mut self, key: K, value: V, edge_tuples: Vec<(K,Option<E>)>, provenance: Provenance)
+        -> Result<InsertOutcome<K,V,E>,LimitError> where K: PartialEq+Clone {
+        let meta = EdgeMeta{ provenance: Some(provenance), ..EdgeMeta::default() };
+        self.try_insert_attributed(key, value, edge_tuples, Some(&meta), InsertOutcome::keyed)
+    }
+
+    /// As `edges`, with the provenance of each edge alongside its destination and measure
+    pub fn edges_with_provenance<Q>(&self, key: &Q) -> Vec<(K,Option<E>,Option<Provenance>)>
+        where K: Borrow<Q>+Clone, Q: ?Sized+PartialEq, E: Clone {
+        let vertex_vec = self.vertex_vec.lock().unwrap();
+        let vertex = match Vertex::find(key, &vertex_vec) {
+            Some(vertex) => vertex,
+            None => return Vec::new(),
+        };
+        let state = vertex.state.lock().unwrap();
+        match *state {
+            VertexState::Resident{ ref edges, .. } => {
+                edges.iter().filter_map(|e| {
+                    let to = e.dest_vertex.key.lock().unwrap().clone()?;
+                    Some((to, e.measure.clone(), e.meta.as_ref().and_then(|m| m.provenance.clone())))
+                }).collect()
+            },
+            VertexState::Phantom => Vec::new(),
+        }
+    }
+
+    /// The provenance of each edge from `from` to `to`, in edge order. Edges created by a plain `insert` yield `None`.
+    pub fn provenance<Q>(&self, from: &Q, to: &Q) -> Vec<Option<Provenance>> where K: Borrow<Q>, Q: ?Sized+PartialEq {
+        let vertex_vec = self.vertex_vec.lock().unwrap();
+        let vertex = match Vertex::find(from, &vertex_vec) {
+            Some(vertex) => vertex,
+            None => return Vec::new(),
+        };
+        let state = vertex.state.lock().unwrap();
+        match *state {
+            VertexState::Resident{ ref edges, .. } => {
+                edges.iter()
+                    .filter(|e| e.dest_vertex.key.lock().unwrap().as_ref().map(Borrow::borrow) == Some(to))
+                    .map(|e| e.meta.as_ref().and_then(|m| m.provenance.clone()))
+                    .collect()
+            },
+            VertexState::Phantom => Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Provenance;
+    use super::super::{DependencyGraph,LimitError,Limits};
+
+    #[test]
+    fn provenance() {
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+        graph.insert("A", "Alpha", vec![]);
+        graph.insert_with_provenance("B", "Bravo", vec![("A",None)], Provenance::Label("manifest.toml".to_string()));
+        graph.insert_with_provenance("C", "Charlie", vec![("A",None),("B",None)], Provenance::Sequence(7));
+        graph.insert("D", "Delta", vec![("A",None)]);
+
+        assert_eq!(graph.provenance(&"B", &"A"), vec![Some(Provenance::Label("manifest.toml".to_string()))]);
+        assert_eq!(graph.provenance(&"C", &"A"), vec![Some(Provenance::Sequence(7))]);
+        assert_eq!(graph.provenance(&"D", &"A"), vec![None]);
+        assert!(graph.provenance(&"A", &"D").is_empty());
+
+        // Re-inserting replaces the edges, and with them their provenance
+        graph.insert("B", "Bravo", vec![("A",None)]);
+        assert_eq!(graph.provenance(&"B", &"A"), vec![None]);
+    }
+
+    #[test]
+    fn edges_with_provenance() {
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::with_limits(Limits{
+            max_edges_per_vertex: Some(2),
+            ..Limits::default()
+        });
+        graph.insert_with_provenance("B", "Bravo", vec![("A",None)], Provenance::Sequence(1));
+        graph.add_edge(&"B", "C", None);
+
+        assert_eq!(graph.edges_with_provenance(&"B"), vec![("A",None,Some(Provenance::Sequence(1))),("C",None,None)]);
+        assert!(graph.edges_with_provenance(&"A").is_empty());
+
+        assert_eq!(graph.try_insert_with_provenance("D", "Delta", vec![("A",None); 3], Provenance::Sequence(2)).err(),
+            Some(LimitError::EdgesPerVertex{ limit: 2 }));
+        assert!(graph.try_insert_with_provenance("D", "Delta", vec![("A",None)], Provenance::Sequence(2)).is_ok());
+        assert_eq!(graph.provenance(&"D", &"A"), vec![Some(Provenance::Sequence(2))]);
+    }
+}
@@ -0,0 +1,399 @@
+//! Removal of vertices, yielding their values by ownership.
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use super::{DependencyGraph,Edge,Vertex,VertexState};
+use adjacency::Adjacency;
+
+/// A vertex, with the key and `residency` it had when it was found
+type Keyed<K,V,E> = (K,usize,Arc<Vertex<K,V,E>>);
+
+/// What `retain` does about the vertices which depend on those it removes, as configured on each handle
+#[derive(Clone,Copy,Debug,Default,PartialEq,Eq)]
+pub enum RemovalPolicy {
+    /// Leave their edges in place, such that the removed vertices remain as phantoms, as with `remove`
+    #[default]
+    KeepPhantoms,
+    /// Drop their edges to the removed vertices, such that no phantom is left behind
+    DropEdges,
+    /// Remove them too, and so on transitively
+    Cascade,
+}
+
+/// Returned by `DependencyGraph::drain_topo`
+pub struct DrainTopo<K,V,E> {
+    graph: DependencyGraph<K,V,E>,
+    /// Vertices yet to be drained
+    pending: VecDeque<Keyed<K,V,E>>,
+}
+
+impl<K,V,E> DependencyGraph<K,V,E> {
+    /// Remove each resident vertex in topological order, yielding its key and value by ownership.
+    ///
+    /// Each vertex is removed as it is yielded, and remains as a phantom while anything not yet yielded depends on it.
+    /// Vertices inserted after the drain began are not yielded, and those not yet yielded when it's dropped remain.
+    pub fn drain_topo(&mut self) -> DrainTopo<K,V,E> where K: Clone {
+        let pending = {
+            let vertex_vec = self.vertex_vec.lock().unwrap();
+            Adjacency::new(&vertex_vec).topo_order().into_iter().filter_map(|i| {
+                let key = vertex_vec[i].key.lock().unwrap().clone()?;
+                Some((key, *vertex_vec[i].residency.lock().unwrap(), vertex_vec[i].clone()))
+            }).collect()
+        };
+        DrainTopo{
+            graph: self.clone(),
+            pending,
+        }
+    }
+
+    /// Remove every resident vertex which `roots` don't transitively depend upon, returning the keys and values collected.
+    /// The roots themselves are retained, as are pinned vertices and their dependencies, and anything reachable through
+    /// masked vertices.
+    pub fn gc(&mut self, roots: &[K]) -> Vec<(K,V)> where K: PartialEq+Clone {
+        let vertex_vec = self.vertex_vec.lock().unwrap();
+        let adjacency = Adjacency::new(&vertex_vec);
+        let mut reached = vec![false; vertex_vec.len()];
+        let mut stack: Vec<usize> = (0..vertex_vec.len())
+            .filter(|&i| {
+                *vertex_vec[i].pinned.lock().unwrap()
+                    || vertex_vec[i].key.lock().unwrap().as_ref().is_some_and(|key| roots.contains(key))
+            })
+            .collect();
+        while let Some(i) = stack.pop() {
+            if !reached[i] {
+                reached[i] = true;
+                stack.extend(adjacency.out[i].iter().cloned().filter(|&j| !reached[j]));
+            }
+        }
+
+        let mut collected = Vec::new();
+        let mut edges = Vec::new();
+        for (vertex, _) in vertex_vec.iter().zip(reached).filter(|p| !p.1) {
+            if let Some((key, value, e)) = vertex.vacate() {
+                collected.push((key, value));
+                edges.push(e);
+            }
+        }
+        // Collected vertices which depend on one another remain as phantoms until now
+        drop(edges);
+        collected
+    }
+
+    /// Remove every resident vertex for which `predicate` returns false, in a single pass. Vertices which depended on
+    /// those removed are dealt with according to this handle's `RemovalPolicy`, except that pinned vertices are neither
+    /// cascaded to nor have their edges dropped, and so keep any removed dependencies as phantoms.
+    ///
+    /// The graph is locked for the duration, so `predicate` must not call back into it.
+    pub fn retain<F>(&mut self, mut predicate: F) where K: Clone, F: FnMut(&K, &V) -> bool {
+        let vertex_vec = self.vertex_vec.lock().unwrap();
+        let mut removing: Vec<bool> = vertex_vec.iter().map(|vertex| {
+            match (vertex.key.lock().unwrap().as_ref(), &*vertex.state.lock().unwrap()) {
+                (Some(key), VertexState::Resident{ value, .. }) => !predicate(key, value),
+                _ => false,
+            }
+        }).collect();
+
+        let adjacency = match self.removal_policy {
+            RemovalPolicy::KeepPhantoms => None,
+            _ => Some(Adjacency::new(&vertex_vec)),
+        };
+        if let (RemovalPolicy::Cascade, Some(adjacency)) = (self.removal_policy, adjacency.as_ref()) {
+            let mut stack: Vec<usize> = (0..removing.len()).filter(|&i| removing[i]).collect();
+            while let Some(i) = stack.pop() {
+                for &j in adjacency.inc[i].iter() {
+                    if !removing[j] && !*vertex_vec[j].pinned.lock().unwrap() {
+                        removing[j] = true;
+                        stack.push(j);
+                    }
+                }
+            }
+        }
+
+        let mut released = Vec::new();
+        for (vertex, _) in vertex_vec.iter().zip(removing.iter()).filter(|p| *p.1) {
+            released.extend(vertex.vacate().into_iter().flat_map(|(_, _, e)| e));
+        }
+        if let (RemovalPolicy::DropEdges, Some(adjacency)) = (self.removal_policy, adjacency.as_ref()) {
+            for (i, vertex) in vertex_vec.iter().enumerate().filter(|(_, vertex)| !*vertex.pinned.lock().unwrap()) {
+                if let VertexState::Resident{ ref mut edges, .. } = *vertex.state.lock().unwrap() {
+                    // The snapshot lists each edge's destination in edge order
+                    let (dangling, kept): (Vec<_>,Vec<_>) = std::mem::take(edges).into_iter()
+                        .zip(adjacency.out[i].iter())
+                        .partition(|&(_, &j)| removing[j]);
+                    *edges = kept.into_iter().map(|(edge, _)| edge).collect();
+                    released.extend(dangling.into_iter().map(|(edge, _)| edge));
+                }
+            }
+        }
+        // Dropped only now that no state lock is held, as removed vertices may still be referenced by one another
+        drop(released);
+    }
+
+    /// How `retain` deals with the dependents of the vertices it removes, when called through this handle
+    pub fn removal_policy(&self) -> RemovalPolicy {
+        self.removal_policy
+    }
+    /// Set how `retain` deals with the dependents of the vertices it removes, when called through this handle
+    pub fn set_removal_policy(&mut self, policy: RemovalPolicy) {
+        self.removal_policy = policy;
+    }
+
+    /// Move the resident vertices matching `predicate` into a new graph, along with their edges, properties, and flags
+    /// such as masking and pinning. Edges crossing between the two graphs become references to phantoms, on whichever
+    /// side the destination is not resident. The new graph's handle has this one's limits and removal policy.
+    ///
+    /// The graph is locked for the duration, so `predicate` must not call back into it.
+    pub fn split_off<F>(&mut self, mut predicate: F) -> DependencyGraph<K,V,E>
+        where K: PartialEq+Clone, F: FnMut(&K, &V) -> bool {
+        let vertex_vec = self.vertex_vec.lock().unwrap();
+        let matched: Vec<Arc<Vertex<K,V,E>>> = vertex_vec.iter().filter(|vertex| {
+            match (vertex.key.lock().unwrap().as_ref(), &*vertex.state.lock().unwrap()) {
+                (Some(key), VertexState::Resident{ value, .. }) => predicate(key, value),
+                _ => false,
+            }
+        }).cloned().collect();
+
+        let mut split = DependencyGraph::new();
+        split.limits = self.limits;
+        split.removal_policy = self.removal_policy;
+        {
+            let mut split_vec = split.vertex_vec.lock().unwrap();
+            for vertex in matched {
+                // Read before `vacate` clears them
+                let dirty = *vertex.dirty.lock().unwrap();
+                let masked = *vertex.masked.lock().unwrap();
+                let pinned = *vertex.pinned.lock().unwrap();
+                let properties = std::mem::take(&mut *vertex.properties.lock().unwrap());
+                let (key, value, mut edges) = match vertex.vacate() {
+                    Some(vacated) => vacated,
+                    None => continue,
+                };
+                // Destinations are still referenced by `edges`, and so remain keyed here, if only as phantoms
+                let split_edges = edges.iter_mut().filter_map(|edge| {
+                    let to = edge.dest_vertex.key.lock().unwrap().clone()?;
+                    let mut split_edge = Edge::new(to, edge.measure.take(), None, &mut split_vec);
+                    split_edge.meta = edge.meta.take();
+                    Some(split_edge)
+                }).collect();
+                drop(edges);
+
+                let split_vertex = Vertex::assert(key, &mut split_vec, VertexState::Phantom);
+                split_vertex.make_resident(value, split_edges);
+                *split_vertex.dirty.lock().unwrap() = dirty;
+                *split_vertex.masked.lock().unwrap() = masked;
+                *split_vertex.pinned.lock().unwrap() = pinned;
+                *split_vertex.properties.lock().unwrap() = properties;
+            }
+        }
+        split
+    }
+}
+
+/// Consume the graph in topological order, as `drain_topo`. Other handles to the graph see it drained likewise.
+impl<K,V,E> IntoIterator for DependencyGraph<K,V,E> where K: PartialEq+Clone {
+    type Item = (K,V);
+    type IntoIter = DrainTopo<K,V,E>;
+
+    fn into_iter(mut self) -> DrainTopo<K,V,E> {
+        self.drain_topo()
+    }
+}
+
+impl<K,V,E> Iterator for DrainTopo<K,V,E> where K: PartialEq+Clone {
+    type Item = (K,V);
+
+    fn next(&mut self) -> Option<(K,V)> {
+        let _vertex_vec = self.graph.vertex_vec.lock().unwrap();
+        while let Some((key, residency, vertex)) = self.pending.pop_front() {
+            // Skip any vertex which has since been removed, whose slot was reused by another key, or which was removed
+            // and then inserted again, and so is no longer the vertex planned
+            if vertex.key.lock().unwrap().as_ref() != Some(&key) || *vertex.residency.lock().unwrap() != residency {
+                continue;
+            }
+            if let Some((key, value, _)) = vertex.vacate() {
+                return Some((key, value));
+            }
+        }
+        None
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.pending.len()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::RemovalPolicy;
+    use super::super::{DependencyGraph,Limits,Property,VertexStatus};
+
+    #[test]
+    fn drain_topo() {
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+        graph.insert("A", "Alpha".to_string(),   vec![]);
+        graph.insert("B", "Bravo".to_string(),   vec![("A",None)]);
+        graph.insert("C", "Charlie".to_string(), vec![("B",None),("Z",None)]);
+
+        let mut drain = graph.drain_topo();
+        assert_eq!(drain.next(), Some(("A", "Alpha".to_string())));
+
+        // A is still depended upon by B, and so remains as a phantom
+        assert_eq!(graph.metrics().phantoms, 2);
+        assert_eq!(graph.iter().count(), 2);
+
+        let rest: Vec<_> = drain.map(|(k, _)| k).collect();
+        assert_eq!(rest, vec!["B","C"]);
+        assert_eq!(graph.iter().count(), 0);
+        assert_eq!(graph.metrics().phantoms, 0, "nothing references the phantoms any more");
+    }
+
+    #[test]
+    fn drain_topo_reinserted() {
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+        graph.insert("A", "Alpha", vec![]);
+        graph.insert("B", "Bravo", vec![]);
+        graph.insert("C", "Charlie", vec![("B",None)]);
+
+        let mut drain = graph.drain_topo();
+        assert_eq!(drain.next(), Some(("B","Bravo")));
+
+        // Removed, freeing its slot, and inserted again into the same one
+        graph.remove("A");
+        graph.insert("A", "Again", vec![]);
+        graph.insert("C", "Charlie Prime", vec![]);
+
+        let rest: Vec<_> = drain.collect();
+        assert_eq!(rest, vec![("C","Charlie Prime")], "updated in place, and so still the vertex planned");
+        assert_eq!(graph.get(&"A"), Some("Again"), "inserted after the drain began, and so not drained");
+    }
+
+    #[test]
+    fn into_iter() {
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+        graph.insert("B", vec![2], vec![("A",None)]);
+        graph.insert("A", vec![1], vec![]);
+
+        let mut values = Vec::new();
+        for (_, value) in graph {
+            values.extend(value);
+        }
+        assert_eq!(values, vec![1,2]);
+    }
+
+    #[test]
+    fn gc() {
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+        graph.insert("A", "Alpha",   vec![("Z",None)]);
+        graph.insert("B", "Bravo",   vec![("A",None)]);
+        graph.insert("C", "Charlie", vec![("D",None)]);
+        graph.insert("D", "Delta",   vec![("C",None),("Y",None)]);
+        graph.insert("E", "Echo",    vec![("A",None)]);
+        graph.mask(&"B");
+
+        let mut collected = graph.gc(&["B"]);
+        collected.sort();
+        assert_eq!(collected, vec![("C","Charlie"),("D","Delta"),("E","Echo")]);
+        assert_eq!((graph.len(), graph.phantom_count()), (2, 1), "Y went with D, which was its only dependent");
+        assert!(graph.gc(&["B"]).is_empty());
+    }
+
+    #[test]
+    fn retain() {
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+        graph.insert("A", 1, vec![]);
+        graph.insert("B", 2, vec![("A",None)]);
+        graph.insert("C", 3, vec![("B",None),("Z",None)]);
+        graph.insert("D", 4, vec![("C",None)]);
+
+        graph.retain(|_, v| v % 2 == 0);
+        let mut keys: Vec<_> = graph.keys().collect();
+        keys.sort();
+        assert_eq!(keys, vec!["B","D"]);
+        assert_eq!(graph.status(&"A"), VertexStatus::Removed, "still depended upon by B");
+        assert_eq!(graph.status(&"Z"), VertexStatus::Missing);
+    }
+
+    #[test]
+    fn retain_policy() {
+        let build = |policy| {
+            let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+            graph.set_removal_policy(policy);
+            graph.insert("A", 1, vec![]);
+            graph.insert("B", 2, vec![("A",None)]);
+            graph.insert("C", 3, vec![("B",None),("Z",None)]);
+            graph.insert("D", 4, vec![("C",None),("A",None),("E",None)]);
+            graph.insert("E", 6, vec![]);
+            graph.retain(|k, _| *k != "A" && *k != "C");
+            graph
+        };
+        let keys = |graph: &DependencyGraph<&'static str,i32,()>| {
+            let mut keys: Vec<_> = graph.keys().collect();
+            keys.sort();
+            keys
+        };
+
+        let graph = build(RemovalPolicy::KeepPhantoms);
+        assert_eq!(keys(&graph), vec!["B","D","E"]);
+        assert_eq!(graph.edges(&"D").len(), 3);
+        assert_eq!(graph.status(&"A"), VertexStatus::Removed);
+        assert_eq!(graph.status(&"C"), VertexStatus::Removed);
+
+        let graph = build(RemovalPolicy::DropEdges);
+        assert_eq!(keys(&graph), vec!["B","D","E"]);
+        assert!(graph.edges(&"B").is_empty());
+        assert_eq!(graph.edges(&"D"), vec![("E",None)], "only the edges to removed vertices are dropped");
+        for key in ["A","C","Z"] {
+            assert_eq!(graph.status(&key), VertexStatus::Missing);
+        }
+        assert_eq!((graph.edge_count(), graph.phantom_count()), (1, 0));
+
+        let graph = build(RemovalPolicy::Cascade);
+        assert_eq!(keys(&graph), vec!["E"], "B depended on A, and D on C");
+        assert_eq!((graph.edge_count(), graph.phantom_count()), (0, 0));
+    }
+
+    #[test]
+    fn split_off() {
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+        graph.insert("A", "Alpha",   vec![]);
+        graph.insert("B", "Bravo",   vec![("A",None)]);
+        graph.insert("C", "Charlie", vec![("B",None)]);
+        graph.insert("D", "Delta",   vec![("C",None)]);
+
+        let split = graph.split_off(|k, _| *k == "B" || *k == "C");
+
+        let keys: Vec<_> = split.iter().map(|i| i.key).collect();
+        assert_eq!(keys, vec!["B","C"]);
+        assert_eq!(split.distance_map(&"C").len(), 3, "C -> B -> A, where A is now a phantom");
+        assert_eq!(split.metrics().phantoms, 1);
+
+        let mut keys: Vec<_> = graph.iter().map(|i| i.key).collect();
+        keys.sort();
+        assert_eq!(keys, vec!["A","D"]);
+        assert_eq!(graph.distance_map(&"D").len(), 2, "D -> C, where C is now a phantom");
+        assert_eq!(graph.metrics().phantoms, 1);
+    }
+
+    #[test]
+    fn split_off_carries_configuration() {
+        let limits = Limits{ max_phantoms: Some(4), ..Limits::default() };
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::with_limits(limits);
+        graph.set_removal_policy(RemovalPolicy::Cascade);
+        graph.insert("A", "Alpha", vec![]);
+        graph.insert("B", "Bravo", vec![("A",None)]);
+        graph.insert("C", "Charlie", vec![]);
+        graph.mask(&"B");
+        graph.pin(&"C");
+        graph.mark_dirty(&"C");
+        graph.set_property(&"C", "owner", "build");
+
+        let split = graph.split_off(|k, _| *k != "A");
+
+        assert_eq!((split.limits(), split.removal_policy()), (limits, RemovalPolicy::Cascade));
+        assert!(split.is_masked(&"B") && !split.is_pinned(&"B"));
+        assert!(split.is_pinned(&"C") && !split.is_masked(&"C"));
+        assert_eq!(split.dirty_closure(), vec!["C"]);
+        assert_eq!(split.property(&"C", "owner"), Some(Property::Str("build".to_string())));
+        assert!(graph.properties(&"C").is_empty());
+    }
+}
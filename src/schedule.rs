@@ -0,0 +1,223 @@
+//! Ordering constraints for system schedulers (ECS and the like).
+//!
+//! Systems register with `before`/`after` constraints in any order. Constraints may name systems which have not
+//! registered yet (an `after` is simply an edge to a phantom), and take effect once they do.
+
+use super::DependencyGraph;
+use adjacency::{Adjacency,strongly_connected};
+
+#[derive(Debug,PartialEq)]
+pub enum ScheduleError<K> {
+    /// These systems' constraints form one or more cycles, and so cannot be ordered. Systems which are only held back
+    /// by a cycle, without being part of one, are not included
+    Cycle(Vec<K>),
+}
+
+pub struct Schedule<K> {
+    graph: DependencyGraph<K,(),()>,
+    /// Registered systems and the systems each must run after
+    systems: Vec<(K,Vec<K>)>,
+    /// `(system, after)` constraints declared via `before` on systems not yet registered
+    pending: Vec<(K,K)>,
+}
+
+/// Returned by `Schedule::add` to declare constraints on the system just added
+pub struct Registration<'a,K: 'a> {
+    schedule: &'a mut Schedule<K>,
+    key: K,
+}
+
+impl<K> Default for Schedule<K> where K: Ord+Clone {
+    fn default() -> Self {
+        Schedule::new()
+    }
+}
+
+impl<K> Schedule<K> where K: Ord+Clone {
+    pub fn new() -> Self {
+        Schedule {
+            graph: DependencyGraph::new(),
+            systems: Vec::new(),
+            pending: Vec::new(),
+        }
+    }
+
+    /// Register a system. Registering an existing system again retains its constraints.
+    pub fn add(&mut self, key: K) -> Registration<'_,K> {
+        if !self.systems.iter().any(|s| s.0 == key) {
+            let mut after = Vec::new();
+            let mut i = 0;
+            while i < self.pending.len() {
+                if self.pending[i].0 == key {
+                    after.push(self.pending.swap_remove(i).1);
+                }else{
+                    i += 1;
+                }
+            }
+            self.systems.push((key.clone(), after));
+            self.sync(&key);
+        }
+        Registration {
+            schedule: self,
+            key,
+        }
+    }
+
+    /// Constrain `key` to run after `after`, which need not be registered yet
+    fn constrain(&mut self, key: K, after: K) {
+        match self.systems.iter_mut().find(|s| s.0 == key) {
+            Some(system) => {
+                if !system.1.contains(&after) {
+                    system.1.push(after);
+                }
+            },
+            None => {
+                self.pending.push((key, after));
+                return;
+            }
+        }
+        self.sync(&key);
+    }
+
+    /// Re-insert a registered system with its current constraints
+    fn sync(&mut self, key: &K) {
+        if let Some(system) = self.systems.iter().find(|s| s.0 == *key) {
+            self.graph.insert(system.0.clone(), (), system.1.iter().map(|k| (k.clone(),None)).collect());
+        }
+    }
+
+    /// Systems referenced by constraints which have not been registered. Their constraints are ignored when ordering.
+    pub fn unregistered(&self) -> Vec<K> {
+        let mut keys: Vec<K> = self.systems.iter().flat_map(|s| s.1.iter())
+            .chain(self.pending.iter().map(|p| &p.0))
+            .filter(|k| !self.systems.iter().any(|s| s.0 == **k))
+            .cloned()
+            .collect();
+        keys.sort();
+        keys.dedup();
+        keys
+    }
+
+    /// Leave a registered system out of `stages` until it's unmasked, without discarding its constraints.
+    /// Systems constrained to run after it are no longer held back by it. Returns false if it isn't registered.
+    pub fn mask(&self, key: &K) -> bool {
+        self.graph.mask(key)
+    }
+    pub fn unmask(&self, key: &K) -> bool {
+        self.graph.unmask(key)
+    }
+
+    /// Order the registered, unmasked systems into stages, such that each system runs in a later stage than everything it must
+    /// run after. Systems within a stage are sorted by key, so the result is deterministic.
+    pub fn stages(&self) -> Result<Vec<Vec<K>>,ScheduleError<K>> {
+        let vertex_vec = self.graph.vertex_vec.lock().unwrap();
+        let adjacency = Adjacency::new(&vertex_vec);
+
+        // Phantom dependencies are unregistered systems and, like masked systems, don't hold anything back
+        let mut ready: Vec<usize> = (0..vertex_vec.len())
+            .filter(|&i| adjacency.active(i) && adjacency.dependencies(i).into_iter().all(|j| !adjacency.active(j)))
+            .collect();
+        let mut staged = vec![false; vertex_vec.len()];
+        let mut stages = Vec::new();
+
+        while !ready.is_empty() {
+            let mut next = Vec::new();
+            for &i in ready.iter() {
+                staged[i] = true;
+            }
+            for &i in ready.iter() {
+                for &d in adjacency.inc[i].iter() {
+                    if adjacency.active(d) && !staged[d] && !next.contains(&d) && adjacency.dependencies(d).into_iter().all(|j| !adjacency.active(j) || staged[j]) {
+                        next.push(d);
+                    }
+                }
+            }
+
+            let mut stage: Vec<K> = ready.iter().filter_map(|&i| vertex_vec[i].key.lock().unwrap().clone()).collect();
+            stage.sort();
+            stages.push(stage);
+            ready = next;
+        }
+
+        if (0..vertex_vec.len()).all(|i| staged[i] || !adjacency.active(i)) {
+            return Ok(stages);
+        }
+
+        // Self-constraints are ignored above, and so don't count as cycles here either
+        let out: Vec<Vec<usize>> = (0..vertex_vec.len()).map(|i| {
+            if adjacency.active(i) { adjacency.dependencies(i).into_iter().filter(|&j| adjacency.active(j)).collect() } else { Vec::new() }
+        }).collect();
+        let mut cyclic: Vec<K> = strongly_connected(&out).into_iter()
+            .filter(|component| component.len() > 1)
+            .flatten()
+            .filter_map(|i| vertex_vec[i].key.lock().unwrap().clone())
+            .collect();
+        cyclic.sort();
+        Err(ScheduleError::Cycle(cyclic))
+    }
+}
+
+impl<'a,K> Registration<'a,K> where K: Ord+Clone {
+    /// This system must run before `other`
+    pub fn before(self, other: K) -> Self {
+        self.schedule.constrain(other, self.key.clone());
+        self
+    }
+    /// This system must run after `other`
+    pub fn after(self, other: K) -> Self {
+        self.schedule.constrain(self.key.clone(), other);
+        self
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Schedule,ScheduleError};
+
+    #[test]
+    fn stages() {
+        let mut schedule = Schedule::new();
+        schedule.add("render").after("physics");
+        schedule.add("input").before("physics").before("ai");
+        schedule.add("audio");
+        assert_eq!(schedule.unregistered(), vec!["ai","physics"]);
+
+        schedule.add("physics");
+        schedule.add("ai").before("render");
+        assert!(schedule.unregistered().is_empty());
+
+        assert_eq!(schedule.stages(), Ok(vec![
+            vec!["audio","input"],
+            vec!["ai","physics"],
+            vec!["render"],
+        ]));
+    }
+
+    #[test]
+    fn cycle() {
+        let mut schedule = Schedule::new();
+        schedule.add("a").after("b");
+        schedule.add("b").after("c");
+        schedule.add("c").after("a");
+        schedule.add("d").after("a");
+        schedule.add("e");
+
+        // d is held back by the cycle, but not part of it
+        assert_eq!(schedule.stages(), Err(ScheduleError::Cycle(vec!["a","b","c"])));
+    }
+
+    #[test]
+    fn mask() {
+        let mut schedule = Schedule::new();
+        schedule.add("render").after("physics");
+        schedule.add("physics").after("input");
+        schedule.add("input");
+
+        assert!(schedule.mask(&"physics"));
+        assert!(!schedule.mask(&"audio"));
+        assert_eq!(schedule.stages(), Ok(vec![vec!["input","render"]]));
+
+        assert!(schedule.unmask(&"physics"));
+        assert_eq!(schedule.stages(), Ok(vec![vec!["input"],vec!["physics"],vec!["render"]]));
+    }
+}
@@ -0,0 +1,159 @@
+//! Incremental construction of a `DependencyGraph` from an asynchronous source of records.
+//!
+//! Because edges may reference vertices which have not arrived yet (they simply become phantoms),
+//! records can be inserted in whatever order the source produces them. The graph handle is shared,
+//! so it may be queried while the load is still in flight.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool,AtomicUsize,Ordering};
+use std::task::{Context,Poll};
+
+use super::DependencyGraph;
+
+/// An asynchronous source of values.
+///
+/// This mirrors the `Stream` trait from `futures-core`, so adapting an existing stream is a one-line `poll_next` forward.
+pub trait Stream {
+    type Item;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>>;
+}
+
+/// Progress of an in-flight `StreamLoad`. Cheap to clone, and remains valid after the load completes.
+#[derive(Clone)]
+pub struct LoadProgress {
+    records: Arc<AtomicUsize>,
+    complete: Arc<AtomicBool>,
+}
+
+impl LoadProgress {
+    fn new() -> Self {
+        LoadProgress {
+            records: Arc::new(AtomicUsize::new(0)),
+            complete: Arc::new(AtomicBool::new(false)),
+        }
+    }
+    /// Number of records inserted so far
+    pub fn records(&self) -> usize {
+        self.records.load(Ordering::SeqCst)
+    }
+    /// True once the source stream has been exhausted
+    pub fn is_complete(&self) -> bool {
+        self.complete.load(Ordering::SeqCst)
+    }
+}
+
+/// Future returned by `DependencyGraph::from_stream`. Resolves to the graph once the stream is exhausted.
+pub struct StreamLoad<K,V,E,S> {
+    graph: DependencyGraph<K,V,E>,
+    stream: S,
+    progress: LoadProgress,
+}
+
+impl<K,V,E,S> StreamLoad<K,V,E,S> {
+    /// A handle to the graph being built. Queries against it observe the records loaded so far.
+    pub fn graph(&self) -> DependencyGraph<K,V,E> {
+        self.graph.clone()
+    }
+    pub fn progress(&self) -> LoadProgress {
+        self.progress.clone()
+    }
+}
+
+impl<K,V,E,S> Future for StreamLoad<K,V,E,S>
+    where K: PartialEq, S: Stream<Item=(K,V,Vec<(K,Option<E>)>)> + Unpin {
+    type Output = DependencyGraph<K,V,E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        loop {
+            match Pin::new(&mut this.stream).poll_next(cx) {
+                Poll::Ready(Some((key, value, edge_tuples))) => {
+                    this.graph.insert_attributed(key, value, edge_tuples, None, drop);
+                    this.progress.records.fetch_add(1, Ordering::SeqCst);
+                },
+                Poll::Ready(None) => {
+                    this.progress.complete.store(true, Ordering::SeqCst);
+                    return Poll::Ready(this.graph.clone());
+                },
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+impl<K,V,E> DependencyGraph<K,V,E> {
+    /// Build a graph from an asynchronous stream of `(key, value, edges)` records.
+    ///
+    /// Records are inserted as they arrive, in whatever order the stream yields them.
+    /// Use `StreamLoad::graph` and `StreamLoad::progress` before awaiting to observe the load mid-flight.
+    pub fn from_stream<S>(stream: S) -> StreamLoad<K,V,E,S>
+        where S: Stream<Item=(K,V,Vec<(K,Option<E>)>)> {
+        StreamLoad {
+            graph: DependencyGraph::new(),
+            stream,
+            progress: LoadProgress::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::VecDeque;
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::task::{Context,Poll,Waker};
+    use super::Stream;
+    use super::super::DependencyGraph;
+
+    /// Yields its records one per poll, returning Pending in between
+    struct Trickle<T> {
+        records: VecDeque<T>,
+        ready: bool,
+    }
+
+    impl<T: Unpin> Stream for Trickle<T> {
+        type Item = T;
+        fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<T>> {
+            if self.ready {
+                self.ready = false;
+                Poll::Ready(self.records.pop_front())
+            }else{
+                self.ready = true;
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        }
+    }
+
+    #[test]
+    fn from_stream() {
+        let stream = Trickle {
+            records: vec![
+                ("B", "Bravo", vec![("A",None)]),
+                ("A", "Alpha", vec![]),
+            ].into_iter().collect(),
+            ready: false,
+        };
+
+        let mut load = DependencyGraph::<&str,&str,()>::from_stream(stream);
+        let progress = load.progress();
+        let mut cx = Context::from_waker(Waker::noop());
+
+        assert!(Pin::new(&mut load).poll(&mut cx).is_pending());
+        assert!(Pin::new(&mut load).poll(&mut cx).is_pending());
+        assert_eq!(progress.records(), 1);
+        assert!(!progress.is_complete());
+
+        let graph = loop {
+            if let Poll::Ready(graph) = Pin::new(&mut load).poll(&mut cx) {
+                break graph;
+            }
+        };
+        assert_eq!(progress.records(), 2);
+        assert!(progress.is_complete());
+        assert_eq!(graph.vertex_vec.lock().unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,142 @@
+//! The topology of a graph without its values, for persisting separately from them.
+//!
+//! `Structure` is plain data, to be written out in whatever format the caller prefers. On load, `hydrate` rebuilds
+//! the graph, obtaining each value from the caller.
+
+use std::collections::HashMap;
+
+use super::{DependencyGraph,Edge,EdgeMeta,Edges,Property,Provenance,Vertex,VertexState};
+
+/// The keys, edges, measures, edge provenance, and vertex properties of a graph. Phantoms are implied by the edges which reference them.
+#[derive(Clone,Debug,PartialEq)]
+pub struct Structure<K,E> {
+    /// Each resident vertex, with its edges in order
+    pub vertices: Vec<(K,Edges<K,E>)>,
+    /// The provenance of those edges which have any, by index into `vertices` and then into that vertex's edges
+    pub provenance: Vec<(usize,usize,Provenance)>,
+    /// The properties of those vertices which have any, by index into `vertices`
+    pub properties: Vec<(usize,Vec<(String,Property)>)>,
+}
+
+impl<K,V,E> DependencyGraph<K,V,E> {
+    /// The structure of the graph as of now
+    pub fn structure(&self) -> Structure<K,E> where K: Clone, E: Clone {
+        let vertex_vec = self.vertex_vec.lock().unwrap();
+        let mut vertices = Vec::new();
+        let mut provenance = Vec::new();
+        let mut properties = Vec::new();
+        for vertex in vertex_vec.iter() {
+            let key = match *vertex.key.lock().unwrap() {
+                Some(ref key) => key.clone(),
+                None => continue,
+            };
+            if let VertexState::Resident{ ref edges, .. } = *vertex.state.lock().unwrap() {
+                let mut keyed = Vec::with_capacity(edges.len());
+                for e in edges.iter() {
+                    if let Some(to) = e.dest_vertex.key.lock().unwrap().clone() {
+                        if let Some(p) = e.meta.as_ref().and_then(|m| m.provenance.clone()) {
+                            provenance.push((vertices.len(), keyed.len(), p));
+                        }
+                        keyed.push((to, e.measure.clone()));
+                    }
+                }
+                let vertex_properties = vertex.properties.lock().unwrap();
+                if !vertex_properties.is_empty() {
+                    properties.push((vertices.len(), vertex_properties.clone()));
+                }
+                vertices.push((key, keyed));
+            }
+        }
+        Structure{ vertices, provenance, properties }
+    }
+
+    /// Rebuild a graph from its structure, with the value of each resident vertex supplied by `value`
+    pub fn hydrate<F>(structure: Structure<K,E>, mut value: F) -> Self where K: PartialEq, F: FnMut(&K) -> V {
+        let graph = DependencyGraph::new();
+        let mut provenance: HashMap<(usize,usize),Provenance> = structure.provenance.into_iter()
+            .map(|(i, j, p)| ((i, j), p))
+            .collect();
+        let mut properties: HashMap<usize,Vec<(String,Property)>> = structure.properties.into_iter().collect();
+        {
+            let mut vertex_vec = graph.vertex_vec.lock().unwrap();
+            for (i, (key, edges)) in structure.vertices.into_iter().enumerate() {
+                let v = value(&key);
+                let edges: Vec<Edge<K,V,E>> = edges.into_iter().enumerate().map(|(j, (to, measure))| {
+                    let meta = provenance.remove(&(i, j)).map(|p| EdgeMeta{ provenance: Some(p), ..EdgeMeta::default() });
+                    Edge::new(to, measure, meta.as_ref(), &mut vertex_vec)
+                }).collect();
+                // A repeated key replaces the earlier vertex, as with `insert`, rather than counting its residency twice
+                let vertex = match Vertex::find(&key, &vertex_vec).filter(|vertex| vertex.is_resident()).cloned() {
+                    Some(vertex) => vertex,
+                    None => Vertex::assert(key, &mut vertex_vec, VertexState::Phantom),
+                };
+                drop(vertex.make_resident(v, edges));
+                if let Some(properties) = properties.remove(&i) {
+                    *vertex.properties.lock().unwrap() = properties;
+                }
+            }
+        }
+        graph
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::{DependencyGraph,Property,Provenance,VertexStatus};
+
+    #[test]
+    fn hydrate() {
+        let mut graph = DependencyGraph::new();
+        graph.insert("A", vec![0u8; 1024], vec![]);
+        graph.insert("B", vec![1u8; 1024], vec![("A",Some(5)),("Z",None)]);
+
+        let structure = graph.structure();
+        assert_eq!(structure.vertices.len(), 2);
+        assert!(structure.vertices.contains(&("B", vec![("A",Some(5)),("Z",None)])));
+
+        let hydrated = DependencyGraph::hydrate(structure.clone(), |k| k.to_lowercase());
+        assert_eq!(hydrated.get(&"B"), Some("b".to_string()));
+        assert_eq!(hydrated.status(&"Z"), VertexStatus::Phantom);
+        assert_eq!(hydrated.structure(), structure);
+    }
+
+    #[test]
+    fn provenance() {
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+        graph.insert("A", "Alpha", vec![]);
+        graph.insert_with_provenance("B", "Bravo", vec![("A",None),("Z",None)], Provenance::Label("lockfile".to_string()));
+        graph.add_edge(&"B", "A", None);
+
+        let structure = graph.structure();
+        let b = structure.vertices.iter().position(|v| v.0 == "B").unwrap();
+        assert_eq!(structure.provenance, vec![
+            (b, 0, Provenance::Label("lockfile".to_string())),
+            (b, 1, Provenance::Label("lockfile".to_string())),
+        ]);
+
+        let hydrated = DependencyGraph::hydrate(structure.clone(), |k| *k);
+        assert_eq!(hydrated.provenance(&"B", &"A"), vec![Some(Provenance::Label("lockfile".to_string())), None]);
+        assert_eq!(hydrated.structure(), structure);
+    }
+
+    #[test]
+    fn properties() {
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+        graph.insert("A", "Alpha", vec![]);
+        graph.insert("B", "Bravo", vec![("A",None)]);
+        graph.set_property(&"B", "owner", "infra");
+        graph.set_property(&"B", "weight", 3);
+
+        let structure = graph.structure();
+        let b = structure.vertices.iter().position(|v| v.0 == "B").unwrap();
+        assert_eq!(structure.properties, vec![(b, vec![
+            ("owner".to_string(), Property::from("infra")),
+            ("weight".to_string(), Property::Int(3)),
+        ])]);
+
+        let hydrated = DependencyGraph::hydrate(structure.clone(), |k| *k);
+        assert_eq!(hydrated.property(&"B", "owner"), Some(Property::from("infra")));
+        assert_eq!(hydrated.properties(&"A"), vec![]);
+        assert_eq!(hydrated.structure(), structure);
+    }
+}
@@ -0,0 +1,113 @@
+//! Extraction of part of a graph as a graph of its own.
+
+use std::collections::{HashMap,VecDeque};
+use std::sync::Arc;
+
+use super::{DependencyGraph,Edge,Vertex,VertexState,VertexVec};
+use adjacency::Adjacency;
+
+/// Which way to follow edges
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum Direction {
+    /// Toward dependencies
+    Upstream,
+    /// Toward dependents
+    Downstream,
+    Both,
+}
+
+impl<K,V,E> DependencyGraph<K,V,E> {
+    /// The subgraph of vertices within `k` hops of `key` in the given direction, including `key` itself.
+    /// Edges are retained only between vertices within the neighborhood. Empty if `key` is absent.
+    pub fn neighborhood(&self, key: &K, k: usize, direction: Direction) -> DependencyGraph<K,V,E>
+        where K: PartialEq+Clone, V: Clone, E: Clone {
+        let vertex_vec = self.vertex_vec.lock().unwrap();
+        let mut included = vec![false; vertex_vec.len()];
+        if let Some(start) = vertex_vec.iter().position(|v| v.key.lock().unwrap().as_ref() == Some(key)) {
+            let adjacency = Adjacency::new(&vertex_vec);
+            let mut queue = VecDeque::new();
+            included[start] = true;
+            queue.push_back((start, 0));
+            while let Some((i, hops)) = queue.pop_front() {
+                if hops == k {
+                    continue;
+                }
+                let upstream = match direction {
+                    Direction::Downstream => &[][..],
+                    _ => &adjacency.out[i][..],
+                };
+                let downstream = match direction {
+                    Direction::Upstream => &[][..],
+                    _ => &adjacency.inc[i][..],
+                };
+                for &j in upstream.iter().chain(downstream.iter()) {
+                    if !included[j] {
+                        included[j] = true;
+                        queue.push_back((j, hops + 1));
+                    }
+                }
+            }
+        }
+        induced(&vertex_vec, &included)
+    }
+}
+
+/// A new graph of the included slots, with the values and edges between them cloned. Included phantoms remain phantoms.
+pub(crate) fn induced<K,V,E>(vertex_vec: &VertexVec<K,V,E>, included: &[bool]) -> DependencyGraph<K,V,E>
+    where K: PartialEq+Clone, V: Clone, E: Clone {
+    let slots: HashMap<*const Vertex<K,V,E>,usize> = vertex_vec.iter().enumerate().map(|(i,v)| (Arc::as_ptr(v), i)).collect();
+    let graph = DependencyGraph::new();
+    {
+        let mut sub_vec = graph.vertex_vec.lock().unwrap();
+        for (vertex, _) in vertex_vec.iter().zip(included).filter(|p| *p.1) {
+            let key = match *vertex.key.lock().unwrap() {
+                Some(ref key) => key.clone(),
+                None => continue,
+            };
+            if let VertexState::Resident{ ref value, ref edges } = *vertex.state.lock().unwrap() {
+                let edges = edges.iter().filter_map(|edge| {
+                    if !included[slots[&Arc::as_ptr(&edge.dest_vertex)]] {
+                        return None;
+                    }
+                    let to = edge.dest_vertex.key.lock().unwrap().clone()?;
+                    Some(Edge::new(to, edge.measure.clone(), edge.meta.as_deref(), &mut sub_vec))
+                }).collect();
+                let sub_vertex = Vertex::assert(key, &mut sub_vec, VertexState::Phantom);
+                sub_vertex.make_resident(value.clone(), edges);
+            }
+        }
+    }
+    graph
+}
+
+#[cfg(test)]
+mod test {
+    use super::Direction;
+    use super::super::DependencyGraph;
+
+    #[test]
+    fn neighborhood() {
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+        graph.insert("A", "Alpha",   vec![]);
+        graph.insert("B", "Bravo",   vec![("A",None)]);
+        graph.insert("C", "Charlie", vec![("B",None),("Z",None)]);
+        graph.insert("D", "Delta",   vec![("C",None)]);
+        graph.insert("E", "Echo",    vec![("D",None)]);
+
+        let keys = |g: DependencyGraph<_,_,()>| {
+            let mut keys: Vec<_> = g.iter().map(|i| i.key).collect();
+            keys.sort();
+            keys
+        };
+        assert_eq!(keys(graph.neighborhood(&"C", 1, Direction::Upstream)), vec!["B","C"]);
+        assert_eq!(keys(graph.neighborhood(&"C", 2, Direction::Downstream)), vec!["C","D","E"]);
+        assert_eq!(keys(graph.neighborhood(&"C", 1, Direction::Both)), vec!["B","C","D"]);
+        assert_eq!(keys(graph.neighborhood(&"C", 0, Direction::Both)), vec!["C"]);
+        assert!(keys(graph.neighborhood(&"X", 3, Direction::Both)).is_empty());
+
+        // Edges out of the neighborhood are dropped, but the phantom within it remains
+        let bubble = graph.neighborhood(&"C", 1, Direction::Upstream);
+        assert_eq!(bubble.metrics().phantoms, 1);
+        assert_eq!(bubble.layout().edges.len(), 2);
+    }
+}
@@ -0,0 +1,66 @@
+//! Derivation of new graphs from existing ones.
+
+use super::{DependencyGraph,Edge,Vertex,VertexState};
+
+impl<K,V,E> DependencyGraph<K,V,E> {
+    /// A new graph with the same vertices and values, where each edge's measure is replaced by that returned by `map`,
+    /// which may itself be None to leave the edge unmeasured, or the edge dropped where `map` returns None. Provenance and
+    /// expiry are carried over, and phantoms referenced only by dropped edges are omitted.
+    ///
+    /// The graph is locked for the duration, so `map` must not call back into it.
+    pub fn map_edges<E2,F>(&self, mut map: F) -> DependencyGraph<K,V,E2>
+        where K: PartialEq+Clone, V: Clone, F: FnMut(&K, &K, &Option<E>) -> Option<Option<E2>> {
+        let vertex_vec = self.vertex_vec.lock().unwrap();
+        let mapped = DependencyGraph::new();
+        {
+            let mut mapped_vec = mapped.vertex_vec.lock().unwrap();
+            for vertex in vertex_vec.iter() {
+                let key = match *vertex.key.lock().unwrap() {
+                    Some(ref key) => key.clone(),
+                    None => continue,
+                };
+                let state = vertex.state.lock().unwrap();
+                if let VertexState::Resident{ ref value, ref edges } = *state {
+                    let edges = edges.iter().filter_map(|edge| {
+                        let to = edge.dest_vertex.key.lock().unwrap().clone()?;
+                        let measure = map(&key, &to, &edge.measure)?;
+                        Some(Edge::new(to, measure, edge.meta.as_deref(), &mut mapped_vec))
+                    }).collect();
+                    let mapped_vertex = Vertex::assert(key, &mut mapped_vec, VertexState::Phantom);
+                    mapped_vertex.make_resident(value.clone(), edges);
+                }
+            }
+        }
+        mapped
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::DependencyGraph;
+
+    #[test]
+    fn map_edges() {
+        let mut graph = DependencyGraph::new();
+        graph.insert("app",   "Application", vec![("lib",Some("normal")),("test",Some("dev")),("Z",Some("dev"))]);
+        graph.insert("lib",   "Library",     vec![("core",None),("test",Some("weak"))]);
+        graph.insert("test",  "Test Harness", vec![]);
+        graph.insert("core",  "Core",        vec![]);
+
+        // A release view, with dev-only dependencies stripped, weak ones left unweighted, and the rest weighted
+        let release = graph.map_edges(|_, _, kind| match *kind {
+            Some("dev") => None,
+            Some("weak") => Some(None),
+            Some(_) => Some(Some(2)),
+            None => Some(Some(1)),
+        });
+
+        let keys: Vec<_> = release.iter().map(|i| i.key).collect();
+        assert_eq!(keys.len(), 4, "all resident vertices survive, but not the phantom");
+        assert!(keys.iter().position(|k| *k == "core") < keys.iter().position(|k| *k == "lib"));
+        assert!(keys.iter().position(|k| *k == "lib") < keys.iter().position(|k| *k == "app"));
+        assert_eq!(release.layout().edges.len(), 3);
+        assert_eq!(release.edges(&"app"), vec![("lib",Some(2))]);
+        assert_eq!(release.edges(&"lib"), vec![("core",Some(1)),("test",None)]);
+    }
+}
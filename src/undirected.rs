@@ -0,0 +1,160 @@
+//! A read-only view of the graph with each edge traversable in both directions.
+
+use super::DependencyGraph;
+use adjacency::Adjacency;
+
+/// Returned by `DependencyGraph::as_undirected`. Phantoms are included as vertices, and self-edges are ignored.
+///
+/// Each query reads the graph as of the call; nothing is retained between them.
+pub struct Undirected<'a,K: 'a,V: 'a,E: 'a> {
+    graph: &'a DependencyGraph<K,V,E>,
+}
+
+impl<K,V,E> DependencyGraph<K,V,E> {
+    pub fn as_undirected(&self) -> Undirected<'_,K,V,E> {
+        Undirected{ graph: self }
+    }
+}
+
+impl<'a,K,V,E> Undirected<'a,K,V,E> {
+    /// Distinct vertices sharing an edge with `key`, in either direction
+    pub fn neighbors(&self, key: &K) -> Vec<K> where K: PartialEq+Clone {
+        let vertex_vec = self.graph.vertex_vec.lock().unwrap();
+        let slot = match vertex_vec.iter().position(|v| v.key.lock().unwrap().as_ref() == Some(key)) {
+            Some(slot) => slot,
+            None => return Vec::new(),
+        };
+        let adjacency = Adjacency::new(&vertex_vec);
+
+        let mut slots: Vec<usize> = Vec::new();
+        for &j in adjacency.out[slot].iter().chain(adjacency.inc[slot].iter()) {
+            if j != slot && !slots.contains(&j) {
+                slots.push(j);
+            }
+        }
+        slots.into_iter().filter_map(|j| vertex_vec[j].key.lock().unwrap().clone()).collect()
+    }
+
+    /// Connected components, each listing its keys
+    pub fn connected_components(&self) -> Vec<Vec<K>> where K: Clone {
+        let vertex_vec = self.graph.vertex_vec.lock().unwrap();
+        let adjacency = Adjacency::new(&vertex_vec);
+
+        let mut seen = vec![false; vertex_vec.len()];
+        let mut components = Vec::new();
+        let mut stack = Vec::new();
+        for root in 0..vertex_vec.len() {
+            if seen[root] || vertex_vec[root].key.lock().unwrap().is_none() {
+                continue;
+            }
+            let mut component = Vec::new();
+            seen[root] = true;
+            stack.push(root);
+            while let Some(i) = stack.pop() {
+                component.extend(vertex_vec[i].key.lock().unwrap().clone());
+                for &j in adjacency.out[i].iter().chain(adjacency.inc[i].iter()) {
+                    if !seen[j] {
+                        seen[j] = true;
+                        stack.push(j);
+                    }
+                }
+            }
+            components.push(component);
+        }
+        components
+    }
+
+    /// Edges whose removal would disconnect their endpoints, as `(from, to)` in the edge's original direction.
+    /// Parallel edges between the same pair of vertices, in either direction, are never bridges.
+    pub fn bridges(&self) -> Vec<(K,K)> where K: Clone {
+        let vertex_vec = self.graph.vertex_vec.lock().unwrap();
+        let adjacency = Adjacency::new(&vertex_vec);
+
+        // Undirected incidence lists of (neighbor, edge id), with self-edges dropped
+        let mut ends = Vec::new();
+        let mut incident: Vec<Vec<(usize,usize)>> = vec![Vec::new(); vertex_vec.len()];
+        for (i, out) in adjacency.out.iter().enumerate() {
+            for &j in out.iter().filter(|&&j| j != i) {
+                incident[i].push((j, ends.len()));
+                incident[j].push((i, ends.len()));
+                ends.push((i, j));
+            }
+        }
+
+        let mut index: Vec<Option<usize>> = vec![None; vertex_vec.len()];
+        let mut low = vec![0; vertex_vec.len()];
+        let mut next_index = 0;
+        let mut bridges = Vec::new();
+        for root in 0..vertex_vec.len() {
+            if index[root].is_some() {
+                continue;
+            }
+            index[root] = Some(next_index);
+            low[root] = next_index;
+            next_index += 1;
+            // Explicit call stack of (vertex, edge id it was reached by, position in its incidence list)
+            let mut calls = vec![(root, usize::MAX, 0)];
+            while let Some(&(v, via, pos)) = calls.last() {
+                if pos < incident[v].len() {
+                    calls.last_mut().unwrap().2 += 1;
+                    let (w, id) = incident[v][pos];
+                    if id == via {
+                        continue;
+                    }
+                    match index[w] {
+                        None => {
+                            index[w] = Some(next_index);
+                            low[w] = next_index;
+                            next_index += 1;
+                            calls.push((w, id, 0));
+                        },
+                        Some(w_index) => low[v] = low[v].min(w_index),
+                    }
+                }else{
+                    calls.pop();
+                    if let Some(&(u, _, _)) = calls.last() {
+                        low[u] = low[u].min(low[v]);
+                        if Some(low[v]) > index[u] {
+                            bridges.push(ends[via]);
+                        }
+                    }
+                }
+            }
+        }
+
+        bridges.into_iter().filter_map(|(i, j)| {
+            Some((vertex_vec[i].key.lock().unwrap().clone()?, vertex_vec[j].key.lock().unwrap().clone()?))
+        }).collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::DependencyGraph;
+
+    #[test]
+    fn undirected() {
+        let mut graph: DependencyGraph<_,_,()> = DependencyGraph::new();
+        // A triangle, bridged from C to D, which depends on a phantom
+        graph.insert("A", "Alpha",   vec![("B",None)]);
+        graph.insert("B", "Bravo",   vec![("C",None)]);
+        graph.insert("C", "Charlie", vec![("A",None)]);
+        graph.insert("D", "Delta",   vec![("C",None),("Z",None),("D",None)]);
+        graph.insert("E", "Echo",    vec![("F",None)]);
+        graph.insert("F", "Foxtrot", vec![("E",None)]);
+
+        let undirected = graph.as_undirected();
+        let mut neighbors = undirected.neighbors(&"C");
+        neighbors.sort();
+        assert_eq!(neighbors, vec!["A","B","D"]);
+        assert_eq!(undirected.neighbors(&"Z"), vec!["D"]);
+
+        let mut components: Vec<Vec<_>> = undirected.connected_components().into_iter().map(|mut c| { c.sort(); c }).collect();
+        components.sort();
+        assert_eq!(components, vec![vec!["A","B","C","D","Z"], vec!["E","F"]]);
+
+        let mut bridges = undirected.bridges();
+        bridges.sort();
+        assert_eq!(bridges, vec![("D","C"),("D","Z")]);
+    }
+}